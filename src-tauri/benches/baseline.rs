@@ -0,0 +1,156 @@
+//! The allocating parser `IrcMessage::parse` used before messages were parsed
+//! borrowed from the raw line, kept to compare against.
+
+use std::collections::HashMap;
+
+#[allow(dead_code)]
+pub struct IrcMessage {
+    pub tags: HashMap<String, String>,
+    pub prefix: Option<IrcPrefix>,
+    pub command: String,
+    pub params: Vec<String>,
+}
+
+#[allow(dead_code)]
+pub enum IrcPrefix {
+    HostOnly {
+        host: String,
+    },
+    Full {
+        nick: String,
+        user: Option<String>,
+        host: Option<String>,
+    },
+}
+
+fn decode_tag_value(raw: &str) -> String {
+    let mut output = String::with_capacity(raw.len());
+
+    let mut iter = raw.chars();
+    while let Some(c) = iter.next() {
+        if c == '\\' {
+            match iter.next() {
+                Some(':') => output.push(';'),
+                Some('s') => output.push(' '),
+                Some('\\') => output.push('\\'),
+                Some('r') => output.push('\r'),
+                Some('n') => output.push('\n'),
+                Some(c) => output.push(c),
+                None => {}
+            }
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
+
+fn parse_tags(source: &str) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+
+    for raw_tag in source.split(';') {
+        let mut tag_split = raw_tag.splitn(2, '=');
+
+        let key = tag_split.next().unwrap();
+        let value = tag_split
+            .next()
+            .map_or_else(|| "".to_owned(), decode_tag_value);
+
+        tags.insert(key.to_owned(), value);
+    }
+
+    tags
+}
+
+fn parse_prefix(source: &str) -> IrcPrefix {
+    if !source.contains('@') {
+        return IrcPrefix::HostOnly {
+            host: source.to_owned(),
+        };
+    }
+
+    let mut at_split = source.splitn(2, '@');
+    let nick_and_user = at_split.next().unwrap();
+    let host = at_split.next();
+
+    let mut exc_split = nick_and_user.splitn(2, '!');
+    let nick = exc_split.next();
+    let user = exc_split.next();
+
+    IrcPrefix::Full {
+        nick: nick.unwrap().to_owned(),
+        user: user.map(|s| s.to_owned()),
+        host: host.map(|s| s.to_owned()),
+    }
+}
+
+pub fn parse(mut source: &str) -> Option<IrcMessage> {
+    if source.chars().any(|c| c == '\r' || c == '\n') {
+        return None;
+    }
+
+    let tags = if let Some(rest) = source.strip_prefix('@') {
+        let (tags_part, remainder) = rest.split_once(' ')?;
+        source = remainder;
+
+        if tags_part.is_empty() {
+            return None;
+        }
+
+        parse_tags(tags_part)
+    } else {
+        HashMap::new()
+    };
+
+    let prefix = if let Some(rest) = source.strip_prefix(':') {
+        let (prefix_part, remainder) = rest.split_once(' ')?;
+        source = remainder;
+
+        if prefix_part.is_empty() {
+            return None;
+        }
+
+        Some(parse_prefix(prefix_part))
+    } else {
+        None
+    };
+
+    let mut command_split = source.splitn(2, ' ');
+    let mut command = command_split.next().unwrap().to_owned();
+    command.make_ascii_uppercase();
+
+    if command.is_empty()
+        || !command.chars().all(|c| c.is_ascii_alphabetic())
+            && !command.chars().all(|c| c.is_ascii() && c.is_numeric())
+    {
+        return None;
+    }
+
+    let mut params = vec![];
+
+    let mut rest = command_split.next();
+    while let Some(rest_str) = rest {
+        if let Some(sub_str) = rest_str.strip_prefix(':') {
+            params.push(sub_str.to_owned());
+            rest = None;
+        } else {
+            let mut split = rest_str.splitn(2, ' ');
+            let param = split.next().unwrap();
+            rest = split.next();
+
+            if param.is_empty() {
+                return None;
+            }
+
+            params.push(param.to_owned());
+        }
+    }
+
+    Some(IrcMessage {
+        tags,
+        prefix,
+        command,
+        params,
+    })
+}
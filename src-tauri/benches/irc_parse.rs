@@ -0,0 +1,67 @@
+mod baseline;
+
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use hypeline_lib::__bench::{IrcMessage, IrcMessageRef, ServerMessage};
+
+/// Lines used when `IRC_PARSE_CORPUS` isn't set. They're shaped like real
+/// traffic but made up, so numbers worth quoting should come from a capture.
+const MESSAGES: &str = include_str!("messages.txt");
+
+/// Reads the lines to parse from the file `IRC_PARSE_CORPUS` points to, one
+/// raw IRC line per line as received from a busy channel.
+fn corpus() -> String {
+    match std::env::var("IRC_PARSE_CORPUS") {
+        Ok(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("Failed to read corpus at {path}: {err}")),
+        Err(_) => MESSAGES.to_owned(),
+    }
+}
+
+fn parse(c: &mut Criterion) {
+    let corpus = corpus();
+    let lines: Vec<&str> = corpus
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let mut group = c.benchmark_group("irc_parse");
+    group.throughput(Throughput::Elements(lines.len() as u64));
+
+    group.bench_function("baseline", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(baseline::parse(black_box(line)));
+            }
+        });
+    });
+
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(IrcMessage::parse(black_box(line)).ok());
+            }
+        });
+    });
+
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(IrcMessageRef::parse(black_box(line)).ok());
+            }
+        });
+    });
+
+    group.bench_function("server_message", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(ServerMessage::parse(black_box(line)).ok());
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
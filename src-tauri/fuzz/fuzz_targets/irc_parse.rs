@@ -0,0 +1,16 @@
+#![no_main]
+
+use hypeline_lib::__bench::{AsRawIrc, IrcMessage, IrcMessageRef, ServerMessage};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let Ok(message) = IrcMessageRef::parse(data) else {
+        return;
+    };
+
+    let _ = ServerMessage::try_from(&message);
+
+    // Anything that parses has to survive being written out and parsed again
+    let owned = IrcMessage::from(&message);
+    assert_eq!(IrcMessage::parse(&owned.as_raw_irc()), Ok(owned));
+});
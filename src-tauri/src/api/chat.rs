@@ -1,13 +1,15 @@
 use anyhow::anyhow;
 use serde::Serialize;
 use serde_json::json;
-use tauri::{State, async_runtime};
+use tauri::{AppHandle, State, async_runtime};
 use tokio::sync::Mutex;
 use twitch_api::HelixClient;
 use twitch_api::eventsub::EventType;
 use twitch_api::extra::AnnouncementColor;
 use twitch_api::helix::bits::{Cheermote, GetCheermotesRequest};
-use twitch_api::helix::chat::{BadgeSet, get_channel_chat_badges, get_global_chat_badges};
+use twitch_api::helix::chat::{
+    BadgeSet, SendChatMessageResponse, get_channel_chat_badges, get_global_chat_badges,
+};
 use twitch_api::helix::streams::Stream;
 use twitch_api::twitch_oauth2::UserToken;
 
@@ -17,12 +19,16 @@ use super::users::{User, get_user_from_login};
 use crate::AppState;
 use crate::emotes::{Emote, EmoteMap, fetch_user_emotes};
 use crate::error::Error;
+use crate::irc::SendError;
+use crate::irc::message::ClientMessage;
 use crate::providers::seventv::{EmoteSet, fetch_active_emote_set, send_presence};
 
 #[derive(Serialize)]
 pub struct JoinedChannel {
-    id: String,
-    user: User,
+    // Channels joined without an access token cannot be looked up, so only
+    // their chat is available.
+    id: Option<String>,
+    user: Option<User>,
     stream: Option<Stream>,
     emotes: EmoteMap,
     emote_set: Option<EmoteSet>,
@@ -30,6 +36,32 @@ pub struct JoinedChannel {
     badges: Vec<BadgeSet>,
 }
 
+#[derive(Serialize)]
+pub struct SentMessage {
+    message_id: Option<String>,
+    is_sent: bool,
+    drop_reason: Option<DropReason>,
+}
+
+#[derive(Serialize)]
+pub struct DropReason {
+    code: String,
+    message: String,
+}
+
+impl From<SendChatMessageResponse> for SentMessage {
+    fn from(response: SendChatMessageResponse) -> Self {
+        Self {
+            message_id: response.message_id.map(|id| id.to_string()),
+            is_sent: response.is_sent,
+            drop_reason: response.drop_reason.map(|reason| DropReason {
+                code: reason.code.to_string(),
+                message: reason.message,
+            }),
+        }
+    }
+}
+
 #[tracing::instrument(skip(state, is_mod))]
 #[tauri::command]
 pub async fn join(
@@ -41,7 +73,6 @@ pub async fn join(
 
     let (helix, token, irc, eventsub, seventv) = {
         let state = state.lock().await;
-        let token = get_access_token(&state)?;
 
         let Some(irc) = state.irc.clone() else {
             tracing::error!("No IRC connection");
@@ -50,13 +81,30 @@ pub async fn join(
 
         (
             state.helix.clone(),
-            token.clone(),
+            state.token.clone(),
             irc,
             state.eventsub.clone(),
             state.seventv.clone(),
         )
     };
 
+    let Some(token) = token else {
+        tracing::info!("No access token set, joining read-only");
+        irc.join(login)
+            .await
+            .map_err(|err| Error::Generic(err.into()))?;
+
+        return Ok(JoinedChannel {
+            id: None,
+            user: None,
+            stream: None,
+            emotes: EmoteMap::default(),
+            emote_set: None,
+            cheermotes: vec![],
+            badges: vec![],
+        });
+    };
+
     let user = match get_user_from_login(state.clone(), login).await? {
         Some(user) => user,
         None => return Err(Error::Generic(anyhow!("User not found"))),
@@ -65,12 +113,17 @@ pub async fn join(
     let broadcaster_id = user.data.id.as_str();
     let login = user.data.login.to_string();
 
-    let (stream, mut emotes, emote_set, cheermotes, badges) = tokio::try_join!(
+    let (stream, mut emotes, emote_set, cheermotes, badges, ()) = tokio::try_join!(
         get_stream(state.clone(), user.data.id.to_string()),
         fetch_user_emotes(broadcaster_id),
         fetch_active_emote_set(broadcaster_id),
         get_cheermotes(&helix, &token, broadcaster_id.to_string()),
-        fetch_channel_badges(&helix, &token, login),
+        fetch_channel_badges(&helix, &token, login.clone()),
+        async {
+            irc.join(login.clone())
+                .await
+                .map_err(|err| Error::Generic(err.into()))
+        },
     )?;
 
     let stv_emotes = match emote_set {
@@ -155,11 +208,9 @@ pub async fn join(
 
     send_presence(state, broadcaster_id.into()).await?;
 
-    irc.join(login.to_string());
-
     Ok(JoinedChannel {
-        id: broadcaster_id.to_string(),
-        user,
+        id: Some(broadcaster_id.to_string()),
+        user: Some(user),
         stream,
         emotes,
         emote_set,
@@ -182,8 +233,20 @@ pub async fn leave(state: State<'_, Mutex<AppState>>, channel: String) -> Result
         seventv.unsubscribe().await;
     }
 
-    if let Some(ref irc) = state.irc {
-        irc.part(channel);
+    let Some(irc) = state.irc.clone() else {
+        return Ok(());
+    };
+
+    drop(state);
+
+    // The channel is left either way, the PART just isn't written when the
+    // connection is gone
+    let part = ClientMessage::Part {
+        channel_login: channel.clone(),
+    };
+
+    if let Err(err) = irc.send(part).await {
+        tracing::warn!(%err, "Failed to part {channel}");
     }
 
     Ok(())
@@ -219,7 +282,10 @@ pub async fn fetch_global_badges(
     tracing::info!("Fetching global badges");
 
     let state = state.lock().await;
-    let token = get_access_token(&state)?;
+
+    let Some(ref token) = state.token else {
+        return Ok(vec![]);
+    };
 
     match state
         .helix
@@ -298,3 +364,91 @@ pub async fn announce(
 
     Ok(())
 }
+
+#[tracing::instrument(skip(app_handle, state, message))]
+#[tauri::command]
+pub async fn send_message(
+    app_handle: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    broadcaster_id: String,
+    login: String,
+    message: String,
+    reply_id: Option<String>,
+    over_irc: bool,
+) -> Result<SentMessage, Error> {
+    // Messages that can't be sent shouldn't take up a spot in the queue
+    let queue = {
+        let state = state.lock().await;
+        get_access_token(&state)?;
+
+        state.queue.clone()
+    };
+
+    // The state must not be held while waiting in the queue
+    queue.wait_turn(&app_handle, &broadcaster_id).await;
+
+    let state = state.lock().await;
+    let token = get_access_token(&state)?;
+
+    if over_irc {
+        let Some(irc) = state.irc.clone() else {
+            return Err(Error::Generic(anyhow!("No IRC connection")));
+        };
+
+        drop(state);
+
+        return match irc.privmsg(login, message, reply_id).await {
+            Ok(message_id) => {
+                tracing::debug!("Message sent");
+
+                Ok(SentMessage {
+                    message_id,
+                    is_sent: true,
+                    drop_reason: None,
+                })
+            }
+            Err(SendError::Rejected { notice_id, message }) => {
+                tracing::warn!(code = %notice_id, "Message dropped: {message}");
+
+                Ok(SentMessage {
+                    message_id: None,
+                    is_sent: false,
+                    drop_reason: Some(DropReason {
+                        code: notice_id.to_string(),
+                        message,
+                    }),
+                })
+            }
+            Err(err) => Err(Error::Generic(err.into())),
+        };
+    }
+
+    let response = match reply_id {
+        Some(reply_id) => {
+            state
+                .helix
+                .send_chat_message_reply(
+                    &broadcaster_id,
+                    &token.user_id,
+                    reply_id.as_str(),
+                    &*message,
+                    token,
+                )
+                .await?
+        }
+        None => {
+            state
+                .helix
+                .send_chat_message(&broadcaster_id, &token.user_id, &*message, token)
+                .await?
+        }
+    };
+
+    if response.is_sent {
+        tracing::debug!("Message sent");
+    } else if let Some(ref reason) = response.drop_reason {
+        tracing::warn!(code = %reason.code, "Message dropped: {}", reason.message);
+    }
+
+    Ok(response.into())
+}
@@ -10,6 +10,7 @@ use twitch_api::helix::moderation::update_shield_mode_status::{
 use twitch_api::twitch_oauth2::TwitchToken;
 
 use super::get_access_token;
+use crate::endpoints::ENDPOINTS;
 use crate::error::Error;
 use crate::{AppState, HTTP};
 
@@ -161,7 +162,7 @@ pub async fn add_moderator(
     let token = get_access_token(&state)?;
 
     let response = HTTP
-        .post("https://api.twitch.tv/helix/moderation/moderators")
+        .post(format!("{}/moderation/moderators", ENDPOINTS.helix))
         .query(&[("broadcaster_id", broadcaster_id), ("user_id", user_id)])
         .header("Client-Id", token.client_id().as_str())
         .bearer_auth(token.access_token.as_str())
@@ -193,7 +194,7 @@ pub async fn remove_moderator(
     let token = get_access_token(&state)?;
 
     let response = HTTP
-        .delete("https://api.twitch.tv/helix/moderation/moderators")
+        .delete(format!("{}/moderation/moderators", ENDPOINTS.helix))
         .query(&[("broadcaster_id", broadcaster_id), ("user_id", user_id)])
         .header("Client-Id", token.client_id().as_str())
         .bearer_auth(token.access_token.as_str())
@@ -23,7 +23,10 @@ pub async fn get_streams(
     ids: Vec<String>,
 ) -> Result<Vec<Stream>, Error> {
     let state = state.lock().await;
-    let token = get_access_token(&state)?;
+
+    let Some(ref token) = state.token else {
+        return Ok(vec![]);
+    };
 
     let streams = state
         .helix
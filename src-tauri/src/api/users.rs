@@ -8,6 +8,7 @@ use twitch_api::helix::users::User as HelixUser;
 use twitch_api::types::{Collection, EmoteAnimationSetting, UserId};
 
 use crate::api::get_access_token;
+use crate::endpoints::ENDPOINTS;
 use crate::error::Error;
 use crate::{AppState, HTTP};
 
@@ -50,7 +51,7 @@ pub async fn get_user_from_id(
 
     if id == token.user_id.to_string() {
         let stv_user = HTTP
-            .get(format!("https://7tv.io/v3/users/twitch/{id}"))
+            .get(format!("{}/users/twitch/{id}", ENDPOINTS.seventv_api))
             .send()
             .await?
             .json::<serde_json::Value>()
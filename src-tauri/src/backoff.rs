@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// Settings for an exponential backoff with jitter.
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffConfig {
+    /// Delay before the first retry.
+    pub initial: Duration,
+    /// Upper bound for the delay between retries.
+    pub max: Duration,
+    /// Factor the delay grows by after every failed attempt.
+    pub multiplier: f64,
+    /// Fraction of the delay that is randomized in either direction, e.g.
+    /// `0.2` for ±20%.
+    pub jitter: f64,
+    /// How long a connection has to stay up before it is considered healthy
+    /// and the backoff starts over.
+    pub reset_after: Duration,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: BackoffConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    pub fn config(&self) -> &BackoffConfig {
+        &self.config
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Returns the delay before the next attempt and advances the backoff.
+    pub fn next_delay(&mut self) -> Duration {
+        let exponent = self.attempt.min(i32::MAX as u32) as i32;
+        let base = self.config.initial.as_secs_f64() * self.config.multiplier.powi(exponent);
+        let base = base.min(self.config.max.as_secs_f64());
+
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::random_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+
+        self.attempt = self.attempt.saturating_add(1);
+
+        Duration::from_secs_f64((base * factor).min(self.config.max.as_secs_f64()))
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::LazyLock;
+
+/// Base URLs of every service the app connects to.
+///
+/// Each endpoint can be overridden with the environment variable next to it,
+/// e.g. to point EventSub at the Twitch CLI's mock server:
+///
+/// ```sh
+/// HYPELINE_EVENTSUB_WS_URL=ws://127.0.0.1:8080/ws \
+/// HYPELINE_EVENTSUB_SUBSCRIPTIONS_URL=http://127.0.0.1:8080/eventsub/subscriptions \
+/// pnpm tauri dev
+/// ```
+///
+/// Building with `USE_LOCAL_EVENTSUB=1` switches the EventSub defaults to
+/// the mock server as well.
+///
+/// `twitch_api`'s Helix client reads its base URL from `TWITCH_HELIX_URL`,
+/// which is set from `HYPELINE_HELIX_URL` at startup unless it's set already.
+pub static ENDPOINTS: LazyLock<Endpoints> = LazyLock::new(Endpoints::from_env);
+
+#[derive(Debug)]
+pub struct Endpoints {
+    /// `HYPELINE_IRC_WS_URL`
+    pub irc_ws: String,
+    /// `HYPELINE_IRC_TLS_ADDR`, as `host:port`.
+    pub irc_tls: String,
+    /// `HYPELINE_HELIX_URL`
+    pub helix: String,
+    /// `HYPELINE_EVENTSUB_WS_URL`
+    pub eventsub_ws: String,
+    /// `HYPELINE_EVENTSUB_SUBSCRIPTIONS_URL`
+    pub eventsub_subscriptions: String,
+    /// `HYPELINE_SEVENTV_API_URL`
+    pub seventv_api: String,
+    /// `HYPELINE_SEVENTV_EVENTS_URL`
+    pub seventv_events: String,
+    /// `HYPELINE_BTTV_API_URL`
+    pub bttv_api: String,
+    /// `HYPELINE_BTTV_CDN_URL`
+    pub bttv_cdn: String,
+    /// `HYPELINE_FFZ_API_URL`
+    pub ffz_api: String,
+    /// `HYPELINE_RECENT_MESSAGES_URL`
+    pub recent_messages: String,
+}
+
+/// Whether the EventSub endpoints default to the Twitch CLI's mock server.
+fn local_eventsub() -> bool {
+    matches!(option_env!("USE_LOCAL_EVENTSUB"), Some("1"))
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        let (eventsub_ws, eventsub_subscriptions) = if local_eventsub() {
+            (
+                "ws://127.0.0.1:8080/ws",
+                "http://127.0.0.1:8080/eventsub/subscriptions",
+            )
+        } else {
+            (
+                "wss://eventsub.wss.twitch.tv/ws",
+                "https://api.twitch.tv/helix/eventsub/subscriptions",
+            )
+        };
+
+        Self {
+            irc_ws: "wss://irc-ws.chat.twitch.tv".into(),
+            irc_tls: "irc.chat.twitch.tv:6697".into(),
+            helix: "https://api.twitch.tv/helix".into(),
+            eventsub_ws: eventsub_ws.into(),
+            eventsub_subscriptions: eventsub_subscriptions.into(),
+            seventv_api: "https://7tv.io/v3".into(),
+            seventv_events: "wss://events.7tv.io/v3".into(),
+            bttv_api: "https://api.betterttv.net/3/cached".into(),
+            bttv_cdn: "https://cdn.betterttv.net/emote".into(),
+            ffz_api: "https://api.frankerfacez.com/v1".into(),
+            recent_messages: "https://recent-messages.robotty.de/api/v2".into(),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let var = |name: &str, default: String| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| value.trim_end_matches('/').to_string())
+                .unwrap_or(default)
+        };
+
+        Self {
+            irc_ws: var("HYPELINE_IRC_WS_URL", defaults.irc_ws),
+            irc_tls: var("HYPELINE_IRC_TLS_ADDR", defaults.irc_tls),
+            helix: var("HYPELINE_HELIX_URL", defaults.helix),
+            eventsub_ws: var("HYPELINE_EVENTSUB_WS_URL", defaults.eventsub_ws),
+            eventsub_subscriptions: var(
+                "HYPELINE_EVENTSUB_SUBSCRIPTIONS_URL",
+                defaults.eventsub_subscriptions,
+            ),
+            seventv_api: var("HYPELINE_SEVENTV_API_URL", defaults.seventv_api),
+            seventv_events: var("HYPELINE_SEVENTV_EVENTS_URL", defaults.seventv_events),
+            bttv_api: var("HYPELINE_BTTV_API_URL", defaults.bttv_api),
+            bttv_cdn: var("HYPELINE_BTTV_CDN_URL", defaults.bttv_cdn),
+            ffz_api: var("HYPELINE_FFZ_API_URL", defaults.ffz_api),
+            recent_messages: var("HYPELINE_RECENT_MESSAGES_URL", defaults.recent_messages),
+        }
+    }
+
+    /// Points `twitch_api`'s Helix client at `helix` if it was overridden.
+    ///
+    /// # Safety
+    ///
+    /// Modifies the environment, so no other thread may be running.
+    pub unsafe fn export_helix_url(&self) {
+        if self.helix == Self::default().helix || std::env::var_os("TWITCH_HELIX_URL").is_some() {
+            return;
+        }
+
+        // The URL is joined with request paths, so it has to end in a slash
+        unsafe { std::env::set_var("TWITCH_HELIX_URL", format!("{}/", self.helix)) };
+    }
+}
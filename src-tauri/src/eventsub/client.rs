@@ -1,34 +1,50 @@
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(test)]
+mod tests;
+
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::anyhow;
 use futures::future::join_all;
-use futures::{SinkExt, StreamExt};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
 use serde::de::{DeserializeOwned, Error as DeError};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::json;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{Mutex, mpsc};
+use tokio::time::Instant;
 use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_util::sync::CancellationToken;
 use tracing::Instrument;
-use twitch_api::eventsub::{EventSubSubscription, EventType};
-use twitch_api::twitch_oauth2::{TwitchToken, UserToken};
 use twitch_api::HelixClient;
+use twitch_api::eventsub::EventType;
+use twitch_api::twitch_oauth2::{TwitchToken, UserToken};
 
-use crate::api::Response;
-use crate::error::Error;
+use super::EventSubStatus;
+use super::event::Event;
+use super::metrics::EventSubMetrics;
+use super::registry::{
+    MAX_SESSIONS, MAX_SUBSCRIPTIONS_PER_SESSION, Placement, SubscriptionKey, SubscriptionRegistry,
+};
 use crate::HTTP;
+use crate::backoff::{Backoff, BackoffConfig};
+use crate::endpoints::ENDPOINTS;
+use crate::error::Error;
+
+/// Keepalive timeout used until the welcome message tells the actual one,
+/// which is also Twitch's default.
+const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
 
-#[cfg(local)]
-const TWITCH_EVENTSUB_WS_URI: &str = "ws://127.0.0.1:8080/ws";
-#[cfg(local)]
-const TWITCH_EVENTSUB_ENDPOINT: &str = "http://127.0.0.1:8080/eventsub/subscriptions";
+/// Extra time given on top of the keepalive timeout before the connection is
+/// considered dead, so a keepalive that is merely late doesn't replace it.
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(5);
 
-#[cfg(not(local))]
-const TWITCH_EVENTSUB_WS_URI: &str = "wss://eventsub.wss.twitch.tv/ws";
-#[cfg(not(local))]
-const TWITCH_EVENTSUB_ENDPOINT: &str = "https://api.twitch.tv/helix/eventsub/subscriptions";
+/// Waited past the reset of Helix's rate limit, so a clock that is slightly
+/// ahead doesn't hit it again.
+const RATE_LIMIT_MARGIN: Duration = Duration::from_secs(1);
 
 const V2_EVENTS: [EventType; 4] = [
     EventType::AutomodMessageHold,
@@ -60,13 +76,72 @@ pub struct MessageMetadata {
 pub struct Subscription {
     pub id: String,
     #[serde(rename = "type")]
-    kind: EventType,
+    pub kind: EventType,
+    pub version: String,
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedSubscription {
+    id: String,
+    cost: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateResponse {
+    data: (CreatedSubscription,),
+    total_cost: u64,
+    max_total_cost: u64,
+}
+
+/// Why a subscription couldn't be created.
+#[derive(Debug)]
+enum CreateError {
+    /// Helix's rate limit was reached, requests are accepted again after the
+    /// delay.
+    RateLimited(Duration),
+    Failed(Error),
+}
+
+impl From<reqwest::Error> for CreateError {
+    fn from(err: reqwest::Error) -> Self {
+        CreateError::Failed(err.into())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedTransport {
+    session_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedSubscription {
+    id: String,
+    version: String,
+    condition: serde_json::Value,
+    transport: ListedTransport,
+    cost: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Pagination {
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    data: Vec<ListedSubscription>,
+    #[serde(default)]
+    pagination: Pagination,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WebSocketSession {
     id: String,
     reconnect_url: Option<String>,
+    /// Seconds Twitch waits without sending anything before it sends a
+    /// keepalive. Not included in reconnect messages.
+    keepalive_timeout_seconds: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,10 +149,15 @@ pub struct SessionPayload {
     session: WebSocketSession,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize)]
 pub struct NotificationPayload {
-    subscription: Subscription,
-    event: serde_json::Value,
+    pub subscription: Subscription,
+    /// The event as Twitch sent it, which is what the webview receives.
+    #[serde(rename = "event")]
+    pub raw_event: serde_json::Value,
+    /// The event parsed for the Rust side.
+    #[serde(skip)]
+    pub event: Event,
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,6 +174,32 @@ pub enum WebSocketMessage {
     Keepalive,
 }
 
+#[derive(Deserialize)]
+struct NotificationDeserializer {
+    subscription: Subscription,
+    event: serde_json::Value,
+}
+
+impl<'de> Deserialize<'de> for NotificationPayload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let NotificationDeserializer {
+            subscription,
+            event,
+        } = NotificationDeserializer::deserialize(deserializer)?;
+
+        let parsed = Event::parse(subscription.kind, &subscription.version, &event);
+
+        Ok(Self {
+            subscription,
+            raw_event: event,
+            event: parsed,
+        })
+    }
+}
+
 #[derive(Deserialize)]
 struct MessageDeserializer {
     metadata: MessageMetadata,
@@ -126,164 +232,449 @@ impl<'de> Deserialize<'de> for WebSocketMessage {
     }
 }
 
+/// Why a connection to a session ended.
+#[derive(Debug, PartialEq, Eq)]
+enum ConnectionEnd {
+    /// Twitch asked to continue the session on another connection.
+    Reconnect,
+    /// The connection failed or was closed, for the given reason.
+    Closed(String),
+    /// No message arrived within the keepalive timeout.
+    Stalled,
+}
+
+/// Where the next connection of a session goes, and whether it continues the
+/// session that asked to reconnect or starts a new one.
+#[derive(Debug)]
+struct SessionTarget {
+    uri: String,
+    reconnecting: bool,
+}
+
+impl SessionTarget {
+    fn new() -> Self {
+        Self {
+            uri: ENDPOINTS.eventsub_ws.clone(),
+            reconnecting: false,
+        }
+    }
+
+    /// Continues the session at the reconnect URL Twitch sent.
+    fn reconnect(&mut self, url: String) {
+        self.uri = url;
+        self.reconnecting = true;
+    }
+
+    /// Starts a new session on the next connection, the subscriptions of the
+    /// old one are created again once it's welcomed.
+    fn reset(&mut self) {
+        self.uri = ENDPOINTS.eventsub_ws.clone();
+        self.reconnecting = false;
+    }
+
+    /// Returns whether the welcomed session continues the previous one.
+    fn welcome(&mut self) -> bool {
+        std::mem::take(&mut self.reconnecting)
+    }
+}
+
 pub struct EventSubClient {
     helix: Arc<HelixClient<'static, reqwest::Client>>,
     pub token: Arc<UserToken>,
-    session_id: Arc<Mutex<Option<String>>>,
-    subscriptions: Arc<Mutex<HashMap<String, String>>>,
+    subscriptions: Arc<Mutex<SubscriptionRegistry>>,
     sender: mpsc::UnboundedSender<NotificationPayload>,
+    status_sender: mpsc::UnboundedSender<EventSubStatus>,
+    /// Indexes of sessions to open once the ones before are full.
+    session_sender: mpsc::UnboundedSender<usize>,
+    session_receiver: Mutex<Option<mpsc::UnboundedReceiver<usize>>>,
+    /// Whether the first session is connected.
     connected: AtomicBool,
-    reconnecting: AtomicBool,
+    /// Cancelled once the client is replaced, which ends all its sessions.
+    shutdown: CancellationToken,
 }
 
 impl EventSubClient {
     pub fn new(
         helix: Arc<HelixClient<'static, reqwest::Client>>,
         token: Arc<UserToken>,
-    ) -> (mpsc::UnboundedReceiver<NotificationPayload>, Self) {
+    ) -> (
+        mpsc::UnboundedReceiver<NotificationPayload>,
+        mpsc::UnboundedReceiver<EventSubStatus>,
+        Self,
+    ) {
         let (sender, receiver) = mpsc::unbounded_channel::<NotificationPayload>();
+        let (status_sender, status_receiver) = mpsc::unbounded_channel();
+        let (session_sender, session_receiver) = mpsc::unbounded_channel();
 
         let client = Self {
             helix,
             token,
-            session_id: Arc::new(Mutex::new(None)),
-            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            subscriptions: Arc::new(Mutex::new(SubscriptionRegistry::new())),
             sender,
+            status_sender,
+            session_sender,
+            session_receiver: Mutex::new(Some(session_receiver)),
             connected: AtomicBool::default(),
-            reconnecting: AtomicBool::default(),
+            shutdown: CancellationToken::new(),
         };
 
-        (receiver, client)
+        (receiver, status_receiver, client)
     }
 
     #[tracing::instrument(name = "eventsub_connect", skip_all)]
     pub async fn connect(self: Arc<Self>) -> Result<(), Error> {
-        let this = Arc::clone(&self);
+        let Some(mut sessions) = self.session_receiver.lock().await.take() else {
+            return Err(Error::Generic(anyhow!("EventSub is already connected")));
+        };
+
+        if let Some(index) = self.subscriptions.lock().await.open() {
+            self.open_session(index);
+        }
 
         tokio::spawn(
             async move {
-                let mut ws_uri = TWITCH_EVENTSUB_WS_URI.to_string();
-                tracing::info!("Connecting to EventSub at {ws_uri}");
-
-                loop {
-                    let mut stream = match connect_async(&ws_uri).await {
-                        Ok((stream, _)) => stream,
-                        Err(e) => {
-                            tracing::error!("Failed to connect to EventSub: {e}");
-                            return Err::<(), _>(Error::WebSocket(e));
+                while let Some(index) = self
+                    .shutdown
+                    .run_until_cancelled(sessions.recv())
+                    .await
+                    .flatten()
+                {
+                    tokio::spawn(
+                        Arc::clone(&self)
+                            .run_session(index)
+                            .instrument(tracing::info_span!("eventsub_session", index)),
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+
+        Ok(())
+    }
+
+    /// Closes every session and stops reconnecting them. Subscriptions end
+    /// with their sessions, so they aren't deleted.
+    pub fn shutdown(&self) {
+        tracing::info!("Shutting down EventSub client");
+
+        self.shutdown.cancel();
+        self.set_connected(false);
+    }
+
+    fn open_session(&self, index: usize) {
+        if self.session_sender.send(index).is_err() {
+            tracing::warn!("EventSub session {index} can't be opened anymore");
+        }
+    }
+
+    /// Keeps the session at `index` connected until the client is shut down.
+    async fn run_session(self: Arc<Self>, index: usize) {
+        // Sessions only end once the client is shut down
+        self.shutdown
+            .run_until_cancelled(Arc::clone(&self).keep_session(index))
+            .await;
+
+        tracing::info!("EventSub session {index} closed");
+
+        if index == 0 {
+            self.set_connected(false);
+        }
+
+        self.subscriptions.lock().await.end_session(index);
+    }
+
+    /// Keeps the session at `index` connected, starting a new one whenever the
+    /// connection is lost.
+    async fn keep_session(self: Arc<Self>, index: usize) {
+        let mut target = SessionTarget::new();
+        let mut backoff = Backoff::new(BackoffConfig::default());
+
+        tracing::info!("Connecting to EventSub at {}", target.uri);
+
+        loop {
+            let mut stream = match connect_async(&target.uri).await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    let delay = backoff.next_delay();
+
+                    tracing::error!(
+                        %err,
+                        attempt = backoff.attempt(),
+                        "Failed to connect to EventSub, retrying in {delay:?}"
+                    );
+
+                    self.send_status(EventSubStatus::Disconnected {
+                        reason: err.to_string(),
+                        retry_in: delay.as_millis() as u64,
+                    });
+
+                    // Reconnect URLs can't be retried, the subscriptions
+                    // are created again on a new session instead
+                    target.reset();
+
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+
+            tracing::info!("Connected to EventSub");
+
+            let connected_at = Instant::now();
+            let end = self.read_connection(index, &mut stream, &mut target).await;
+
+            if connected_at.elapsed() >= backoff.config().reset_after {
+                backoff.reset();
+            }
+
+            let reason = match end {
+                ConnectionEnd::Reconnect => {
+                    self.send_status(EventSubStatus::Disconnected {
+                        reason: String::from("Reconnect requested"),
+                        retry_in: 0,
+                    });
+
+                    continue;
+                }
+                ConnectionEnd::Closed(reason) => reason,
+                ConnectionEnd::Stalled => String::from("Keepalive timed out"),
+            };
+
+            target.reset();
+
+            let delay = backoff.next_delay();
+            tracing::info!("Reconnecting to EventSub in {delay:?}");
+
+            self.send_status(EventSubStatus::Disconnected {
+                reason,
+                retry_in: delay.as_millis() as u64,
+            });
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Handles the messages of one connection to the session at `index` until
+    /// it ends.
+    async fn read_connection<S>(
+        self: &Arc<Self>,
+        index: usize,
+        stream: &mut S,
+        target: &mut SessionTarget,
+    ) -> ConnectionEnd
+    where
+        S: Stream<Item = Result<Message, WsError>> + Sink<Message, Error = WsError> + Unpin,
+    {
+        if index == 0 {
+            self.set_connected(true);
+        }
+
+        let end = self.read_messages(index, stream, target).await;
+
+        if index == 0 {
+            self.set_connected(false);
+        }
+
+        self.subscriptions.lock().await.end_session(index);
+
+        end
+    }
+
+    async fn read_messages<S>(
+        self: &Arc<Self>,
+        index: usize,
+        stream: &mut S,
+        target: &mut SessionTarget,
+    ) -> ConnectionEnd
+    where
+        S: Stream<Item = Result<Message, WsError>> + Sink<Message, Error = WsError> + Unpin,
+    {
+        let mut keepalive_timeout = DEFAULT_KEEPALIVE_TIMEOUT;
+
+        loop {
+            // Any message shows the connection is alive, without one a
+            // half-open connection would be waited on forever
+            let message = match tokio::time::timeout(
+                keepalive_timeout + KEEPALIVE_GRACE,
+                stream.next(),
+            )
+            .await
+            {
+                Ok(Some(Ok(message))) => message,
+                Ok(Some(Err(err))) => {
+                    tracing::warn!(%err, "EventSub connection failed");
+                    return ConnectionEnd::Closed(err.to_string());
+                }
+                Ok(None) => return ConnectionEnd::Closed(String::from("Connection closed")),
+                Err(_) => {
+                    tracing::warn!(
+                        "No EventSub message received in {keepalive_timeout:?}, reconnecting"
+                    );
+
+                    self.send_status(EventSubStatus::Stalled {
+                        timeout: keepalive_timeout.as_millis() as u64,
+                    });
+
+                    return ConnectionEnd::Stalled;
+                }
+            };
+
+            match message {
+                Message::Ping(data) => {
+                    if let Err(err) = stream.send(Message::Pong(data)).await {
+                        tracing::warn!(%err, "Failed to answer EventSub ping");
+                        return ConnectionEnd::Closed(err.to_string());
+                    }
+                }
+                Message::Text(data) => {
+                    let msg = match serde_json::from_str(data.as_str()) {
+                        Ok(msg) => msg,
+                        Err(err) => {
+                            tracing::warn!(%err, "Skipping malformed EventSub message");
+                            continue;
                         }
                     };
 
-                    tracing::info!("Connected to EventSub");
-                    self.set_connected(true);
-
-                    let mut reconnect_url: Option<String> = None;
-
-                    while let Some(Ok(message)) = stream.next().await {
-                        let this = Arc::clone(&this);
-
-                        match message {
-                            Message::Ping(data) => {
-                                stream.send(Message::Pong(data)).await?;
-                            }
-                            Message::Text(data) => {
-                                if let Ok(msg) = serde_json::from_str(data.as_str()) {
-                                    match this.handle_message(msg).await? {
-                                        Some(url) => {
-                                            reconnect_url = Some(url);
-                                            break;
-                                        }
-                                        None => continue,
-                                    }
-                                }
-                            }
-                            Message::Close(Some(frame)) => {
-                                tracing::warn!(%frame, "EventSub connection closed");
-                                break;
-                            }
-                            Message::Close(None) => {
-                                tracing::warn!("EventSub connection closed");
-                                break;
-                            }
-                            _ => (),
-                        }
+                    if let WebSocketMessage::Welcome(SessionPayload {
+                        session:
+                            WebSocketSession {
+                                keepalive_timeout_seconds: Some(seconds),
+                                ..
+                            },
+                    }) = &msg
+                    {
+                        keepalive_timeout = Duration::from_secs(*seconds);
                     }
 
-                    self.set_connected(false);
-                    *self.session_id.lock().await = None;
-
-                    if let Some(url) = reconnect_url {
-                        ws_uri = url;
+                    match Arc::clone(self).handle_message(index, target, msg).await {
+                        Ok(Some(url)) => {
+                            target.reconnect(url);
+                            return ConnectionEnd::Reconnect;
+                        }
+                        Ok(None) => continue,
+                        Err(err) => {
+                            tracing::error!(%err, "Failed to handle EventSub message");
+                        }
                     }
                 }
+                Message::Close(Some(frame)) => {
+                    tracing::warn!(%frame, "EventSub connection closed");
+                    return ConnectionEnd::Closed(frame.to_string());
+                }
+                Message::Close(None) => {
+                    tracing::warn!("EventSub connection closed");
+                    return ConnectionEnd::Closed(String::from("Connection closed"));
+                }
+                _ => (),
             }
-            .in_current_span(),
-        );
-
-        Ok(())
+        }
     }
 
     #[tracing::instrument(skip_all)]
     async fn handle_message(
         self: Arc<Self>,
+        index: usize,
+        target: &mut SessionTarget,
         msg: WebSocketMessage,
     ) -> Result<Option<String>, Error> {
         use WebSocketMessage as Ws;
 
         match msg {
             Ws::Welcome(payload) => {
-                tracing::debug!("Set EventSub session id to {}", payload.session.id);
-                *self.session_id.lock().await = Some(payload.session.id);
+                let session_id = payload.session.id;
+                let resumed = target.welcome();
 
-                if self
-                    .reconnecting
-                    .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
-                    .is_err()
-                {
-                    tracing::info!("Initial connection to EventSub established");
+                tracing::debug!("Set EventSub session {index} id to {session_id}");
+
+                // Subscriptions don't carry over to a new session. They're
+                // marked as pending right away, so they aren't created twice
+                let subscriptions = self.subscriptions.lock().await.start_session(
+                    index,
+                    session_id.clone(),
+                    resumed,
+                );
+
+                self.send_status(EventSubStatus::Connected {
+                    session_id: session_id.clone(),
+                });
+
+                if resumed {
+                    tracing::info!("Reconnected to EventSub");
+                } else {
+                    tracing::info!("New EventSub session started");
+                }
+
+                let this = Arc::clone(&self);
+
+                tokio::spawn(
+                    async move { this.resubscribe(session_id, subscriptions).await }
+                        .in_current_span(),
+                );
 
+                if index == 0 && !resumed {
                     self.subscribe(
-                        self.token.login.as_str(),
+                        None,
                         EventType::UserUpdate,
                         json!({ "user_id": self.token.user_id }),
                     )
                     .await?;
-                } else {
-                    tracing::info!("Reconnected to EventSub");
                 }
             }
             Ws::Notification(payload) => {
                 tracing::trace!(
-                    "Received {} event: {}",
+                    "Received {} event: {:?}",
                     payload.subscription.kind,
                     payload.event
                 );
 
-                self.sender.send(payload).unwrap();
+                if let Event::UserUpdate(user) = &payload.event {
+                    tracing::info!(login = %user.user_login, "User account was updated");
+                }
+
+                // Events still in flight after their channel was left would
+                // be shown in whichever channel the webview is in now
+                if let Some(channel) = payload.event.broadcaster_login()
+                    && !self.subscriptions.lock().await.has_channel(channel)
+                {
+                    tracing::debug!(
+                        "Dropping {} event of {channel}, which was left",
+                        payload.subscription.kind
+                    );
+
+                    return Ok(None);
+                }
+
+                if self.sender.send(payload).is_err() {
+                    tracing::warn!("EventSub notification receiver is gone");
+                }
             }
             Ws::Reconnect(payload) => {
                 tracing::warn!("Reconnect requested for {}", payload.session.id);
 
-                let url = payload
-                    .session
-                    .reconnect_url
-                    .expect("missing reconnect_url in reconnect payload");
-
-                self.reconnecting.store(true, Ordering::Relaxed);
+                let Some(url) = payload.session.reconnect_url else {
+                    return Err(Error::Generic(anyhow!(
+                        "Missing reconnect_url in reconnect payload"
+                    )));
+                };
 
                 return Ok(Some(url));
             }
             Ws::Revocation(payload) => {
-                tracing::warn!(
-                    "Revocation requested for {} ({})",
-                    payload.subscription.kind,
-                    payload.subscription.id
-                );
+                let Subscription {
+                    id, kind, status, ..
+                } = payload.subscription;
 
-                self.subscriptions
+                tracing::warn!("Revocation requested for {kind} ({id}): {status}");
+
+                // Kept until the channel is left so it isn't deleted again
+                if self
+                    .subscriptions
                     .lock()
                     .await
-                    .remove(&payload.subscription.kind.to_string());
+                    .revoke(&id, status)
+                    .is_none()
+                {
+                    tracing::warn!("Revoked subscription {id} is unknown");
+                }
             }
             _ => (),
         }
@@ -291,6 +682,12 @@ impl EventSubClient {
         Ok(None)
     }
 
+    fn send_status(&self, status: EventSubStatus) {
+        if self.status_sender.send(status).is_err() {
+            tracing::warn!("EventSub status receiver is gone");
+        }
+    }
+
     pub fn connected(&self) -> bool {
         self.connected.load(Ordering::Relaxed)
     }
@@ -302,18 +699,159 @@ impl EventSubClient {
     #[tracing::instrument(name = "eventsub_subscribe", skip(self, condition), fields(%condition))]
     pub async fn subscribe(
         &self,
-        username: &str,
+        channel: Option<&str>,
         event: EventType,
         condition: serde_json::Value,
     ) -> Result<(), Error> {
-        let session_id = self.session_id.lock().await;
+        let version = if V2_EVENTS.contains(&event) { "2" } else { "1" };
+        let key = SubscriptionKey::new(channel, event, version, &condition);
 
-        let Some(session_id) = session_id.as_ref() else {
-            return Err(Error::Generic(anyhow!("No EventSub connection")));
-        };
+        let placement = self
+            .subscriptions
+            .lock()
+            .await
+            .start(key.clone(), condition.clone());
 
-        let version = if V2_EVENTS.contains(&event) { "2" } else { "1" };
+        self.create(&key, placement, &condition).await
+    }
+
+    /// Creates a subscription on the session the registry assigned it to,
+    /// moving it to another session if Twitch refuses to take more.
+    async fn create(
+        &self,
+        key: &SubscriptionKey,
+        mut placement: Placement,
+        condition: &serde_json::Value,
+    ) -> Result<(), Error> {
+        loop {
+            let session_id = match placement {
+                Placement::Exists => {
+                    tracing::trace!("Already subscribed");
+                    return Ok(());
+                }
+                Placement::Create(session_id) => session_id,
+                Placement::Queued => {
+                    tracing::trace!("Subscription queued until its session started");
+                    return Ok(());
+                }
+                Placement::Open(index) => {
+                    tracing::info!("EventSub sessions are full, opening session {index}");
+                    self.open_session(index);
+
+                    return Ok(());
+                }
+                Placement::Full => {
+                    let err = Error::Generic(anyhow!(
+                        "All {MAX_SESSIONS} EventSub sessions hold {MAX_SUBSCRIPTIONS_PER_SESSION} subscriptions"
+                    ));
+
+                    return Err(self.fail(key, err).await);
+                }
+            };
+
+            let err = match self
+                .create_subscription(&session_id, key.event, key.version, condition)
+                .await
+            {
+                Ok(response) => return self.created(key, response).await,
+                Err(CreateError::RateLimited(delay)) => {
+                    tracing::warn!("Helix rate limit reached, subscribing again in {delay:?}");
+                    tokio::time::sleep(delay).await;
+
+                    placement = Placement::Create(session_id);
+                    continue;
+                }
+                Err(CreateError::Failed(err)) => err,
+            };
+
+            // Another client created the same subscription on the session, it
+            // needs its id to be deleted once the channel is left
+            if http_status(&err) == Some(StatusCode::CONFLICT) {
+                tracing::trace!("Subscription already exists, looking up its id");
+
+                return match self.find_subscription(&session_id, key, condition).await {
+                    Ok(Some(existing)) => self.enable(key, existing.id, existing.cost).await,
+                    Ok(None) => Err(self.fail(key, err).await),
+                    Err(err) => Err(self.fail(key, err).await),
+                };
+            }
+
+            let mut subscriptions = self.subscriptions.lock().await;
+
+            placement = match http_status(&err) {
+                // Without the cost being used up, the session can't take more
+                Some(StatusCode::TOO_MANY_REQUESTS) if !subscriptions.cost_exhausted() => {
+                    tracing::warn!("EventSub session is full, moving subscription");
+                    subscriptions.session_full(key)
+                }
+                _ => {
+                    drop(subscriptions);
+                    return Err(self.fail(key, err).await);
+                }
+            };
+        }
+    }
+
+    async fn created(&self, key: &SubscriptionKey, response: CreateResponse) -> Result<(), Error> {
+        let CreateResponse {
+            data: (created,),
+            total_cost,
+            max_total_cost,
+        } = response;
 
+        self.subscriptions
+            .lock()
+            .await
+            .set_cost(total_cost, max_total_cost);
+
+        tracing::trace!(
+            cost = created.cost,
+            total_cost,
+            max_total_cost,
+            "Subscription created"
+        );
+
+        self.enable(key, created.id, created.cost).await
+    }
+
+    async fn enable(&self, key: &SubscriptionKey, id: String, cost: u64) -> Result<(), Error> {
+        // The channel was left while the subscription was being created
+        if !self
+            .subscriptions
+            .lock()
+            .await
+            .enable(key, id.clone(), cost)
+        {
+            tracing::trace!("Subscription no longer wanted");
+
+            return self.delete_subscription(id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the subscription as failed and tells the webview about it.
+    async fn fail(&self, key: &SubscriptionKey, err: Error) -> Error {
+        tracing::warn!(%err, "Failed to subscribe to {}", key.event);
+
+        self.subscriptions.lock().await.fail(key, err.to_string());
+
+        self.send_status(EventSubStatus::SubscriptionFailed {
+            channel: key.channel.clone(),
+            event: key.event,
+            error: err.to_string(),
+        });
+
+        err
+    }
+
+    async fn create_subscription(
+        &self,
+        session_id: &str,
+        event: EventType,
+        version: &str,
+        condition: &serde_json::Value,
+    ) -> Result<CreateResponse, CreateError> {
         let body = json!({
             "type": event,
             "version": version,
@@ -324,22 +862,94 @@ impl EventSubClient {
             }
         });
 
-        let response: Response<(EventSubSubscription,)> = HTTP
-            .post(TWITCH_EVENTSUB_ENDPOINT)
+        let response = HTTP
+            .post(&ENDPOINTS.eventsub_subscriptions)
             .bearer_auth(self.token.access_token.as_str())
             .header("Client-Id", self.token.client_id().as_str())
             .json(&body)
             .send()
-            .await?
-            .json()
             .await?;
 
-        self.subscriptions
-            .lock()
-            .await
-            .insert(format!("{username}:{event}"), response.data.0.id.take());
+        if let Some(delay) =
+            rate_limit_delay(response.status(), response.headers(), SystemTime::now())
+        {
+            return Err(CreateError::RateLimited(delay));
+        }
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Looks up a subscription Twitch already has on the session, matching
+    /// the one that would be created.
+    async fn find_subscription(
+        &self,
+        session_id: &str,
+        key: &SubscriptionKey,
+        condition: &serde_json::Value,
+    ) -> Result<Option<ListedSubscription>, Error> {
+        let mut cursor = None;
+
+        loop {
+            let mut request = HTTP
+                .get(&ENDPOINTS.eventsub_subscriptions)
+                .bearer_auth(self.token.access_token.as_str())
+                .header("Client-Id", self.token.client_id().as_str())
+                .query(&[("type", key.event.to_string())]);
+
+            if let Some(cursor) = &cursor {
+                request = request.query(&[("after", cursor)]);
+            }
+
+            let response: ListResponse = request.send().await?.error_for_status()?.json().await?;
+
+            // Twitch lists conditions with every field of the type, including
+            // those that weren't set
+            let existing = response.data.into_iter().find(|subscription| {
+                subscription.version == key.version
+                    && subscription.transport.session_id.as_deref() == Some(session_id)
+                    && condition.as_object().is_some_and(|fields| {
+                        fields
+                            .iter()
+                            .all(|(name, value)| subscription.condition.get(name) == Some(value))
+                    })
+            });
+
+            if existing.is_some() {
+                return Ok(existing);
+            }
+
+            match response.pagination.cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Creates the subscriptions of the previous session again, since
+    /// subscriptions only live as long as the session they were created on.
+    async fn resubscribe(
+        &self,
+        session_id: String,
+        subscriptions: Vec<(SubscriptionKey, serde_json::Value)>,
+    ) {
+        if subscriptions.is_empty() {
+            return;
+        }
+
+        tracing::info!("Resubscribing to {} EventSub events", subscriptions.len());
+
+        // Failures are reported as they happen
+        let futures = subscriptions.iter().map(|(key, condition)| {
+            self.create(key, Placement::Create(session_id.clone()), condition)
+        });
+
+        join_all(futures).await;
+    }
 
-        tracing::trace!("Subscription created");
+    async fn delete_subscription(&self, id: String) -> Result<(), Error> {
+        self.helix
+            .delete_eventsub_subscription(id, &*self.token)
+            .await?;
 
         Ok(())
     }
@@ -351,42 +961,59 @@ impl EventSubClient {
     ) -> Result<(), Error> {
         let futures = subscriptions
             .iter()
-            .map(|&(event, condition)| self.subscribe(channel, event, condition.clone()));
+            .map(|&(event, condition)| self.subscribe(Some(channel), event, condition.clone()));
 
         join_all(futures).await;
 
         Ok(())
     }
 
-    pub async fn unsubscribe(&self, channel: &str, event: String) -> Result<(), Error> {
-        let id = self
-            .subscriptions
-            .lock()
-            .await
-            .remove(&format!("{channel}:{event}"));
+    pub async fn metrics(&self) -> EventSubMetrics {
+        self.subscriptions.lock().await.metrics()
+    }
 
-        if let Some(id) = id {
-            self.helix
-                .delete_eventsub_subscription(id, &*self.token)
-                .await?;
+    /// Removes the subscriptions created for the channel, leaving those of
+    /// other channels untouched.
+    pub async fn unsubscribe_all(&self, channel: &str) -> Result<(), Error> {
+        let removed = self.subscriptions.lock().await.remove_channel(channel);
+
+        // Revoked and failed subscriptions don't exist on Twitch's side, and
+        // pending ones are deleted once they were created
+        let futures = removed
+            .into_iter()
+            .filter(|(_, entry)| entry.is_active())
+            .filter_map(|(_, entry)| entry.id)
+            .map(|id| self.delete_subscription(id));
+
+        for result in join_all(futures).await {
+            if let Err(err) = result {
+                tracing::warn!(%err, "Failed to delete subscription of {channel}");
+            }
         }
 
         Ok(())
     }
+}
 
-    pub async fn unsubscribe_all(&self, channel: &str) -> Result<(), Error> {
-        let keys = {
-            let subscriptions = self.subscriptions.lock().await;
+/// Time until Helix accepts requests again, if the response was refused
+/// because its rate limit was reached. A full session or used up cost are
+/// refused with the same status, but leave requests remaining.
+fn rate_limit_delay(status: StatusCode, headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
 
-            subscriptions.keys().cloned().collect::<Vec<_>>()
-        };
+    if status != StatusCode::TOO_MANY_REQUESTS || header("ratelimit-remaining")? != 0 {
+        return None;
+    }
 
-        let futures = keys
-            .iter()
-            .map(|event| self.unsubscribe(channel, event.into()));
+    // Seconds since the epoch at which the bucket is refilled
+    let reset = UNIX_EPOCH + Duration::from_secs(header("ratelimit-reset").unwrap_or_default());
 
-        join_all(futures).await;
+    Some(reset.duration_since(now).unwrap_or_default() + RATE_LIMIT_MARGIN)
+}
 
-        Ok(())
+fn http_status(err: &Error) -> Option<StatusCode> {
+    match err {
+        Error::Http(err) => err.status(),
+        _ => None,
     }
 }
@@ -0,0 +1,311 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{Sink, Stream};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use twitch_api::HelixClient;
+use twitch_api::twitch_oauth2::{AccessToken, ClientId, ClientSecret, RefreshToken, UserToken};
+
+use super::{ConnectionEnd, EventSubClient, RATE_LIMIT_MARGIN, SessionTarget, rate_limit_delay};
+use crate::endpoints::ENDPOINTS;
+use crate::eventsub::EventSubStatus;
+
+/// A WebSocket connection fed by the test, which accepts everything written
+/// to it.
+struct StubSocket {
+    incoming: mpsc::UnboundedReceiver<Result<Message, WsError>>,
+}
+
+impl Stream for StubSocket {
+    type Item = Result<Message, WsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_recv(cx)
+    }
+}
+
+impl Sink<Message> for StubSocket {
+    type Error = WsError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, _: Message) -> Result<(), WsError> {
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), WsError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn stub_socket() -> (mpsc::UnboundedSender<Result<Message, WsError>>, StubSocket) {
+    let (incoming_tx, incoming) = mpsc::unbounded_channel();
+
+    (incoming_tx, StubSocket { incoming })
+}
+
+fn client() -> (mpsc::UnboundedReceiver<EventSubStatus>, Arc<EventSubClient>) {
+    let token = UserToken::from_existing_unchecked(
+        AccessToken::from(String::from("token")),
+        None::<RefreshToken>,
+        ClientId::from(String::from("client")),
+        None::<ClientSecret>,
+        String::from("justinfan12345").into(),
+        String::from("12345").into(),
+        None,
+        None,
+    );
+
+    let (_, status, client) = EventSubClient::new(Arc::new(HelixClient::new()), Arc::new(token));
+
+    (status, Arc::new(client))
+}
+
+fn text(message: serde_json::Value) -> Result<Message, WsError> {
+    Ok(Message::Text(message.to_string().into()))
+}
+
+fn welcome(session_id: &str, keepalive_timeout_seconds: Option<u64>) -> Result<Message, WsError> {
+    text(json!({
+        "metadata": { "message_type": "session_welcome" },
+        "payload": {
+            "session": {
+                "id": session_id,
+                "reconnect_url": null,
+                "keepalive_timeout_seconds": keepalive_timeout_seconds,
+            }
+        }
+    }))
+}
+
+fn keepalive() -> Result<Message, WsError> {
+    text(json!({
+        "metadata": { "message_type": "session_keepalive" },
+        "payload": {}
+    }))
+}
+
+fn reconnect(url: &str) -> Result<Message, WsError> {
+    text(json!({
+        "metadata": { "message_type": "session_reconnect" },
+        "payload": {
+            "session": {
+                "id": "first",
+                "reconnect_url": url,
+                "keepalive_timeout_seconds": null,
+            }
+        }
+    }))
+}
+
+fn statuses(status: &mut mpsc::UnboundedReceiver<EventSubStatus>) -> Vec<EventSubStatus> {
+    std::iter::from_fn(|| status.try_recv().ok()).collect()
+}
+
+// The sessions below aren't the first one, which would subscribe to the
+// user's updates on every new session
+
+#[tokio::test(start_paused = true)]
+async fn silent_connections_stall() {
+    let (mut status, client) = client();
+    let (incoming_tx, mut socket) = stub_socket();
+    let mut target = SessionTarget::new();
+
+    incoming_tx.send(welcome("first", Some(10))).unwrap();
+
+    let started_at = Instant::now();
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(end, ConnectionEnd::Stalled);
+    assert_eq!(started_at.elapsed(), Duration::from_secs(15));
+
+    assert!(matches!(
+        &statuses(&mut status)[..],
+        [
+            EventSubStatus::Connected { session_id },
+            EventSubStatus::Stalled { timeout: 10_000 },
+        ] if session_id == "first"
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn connections_stall_before_the_welcome() {
+    let (mut status, client) = client();
+    let (_incoming_tx, mut socket) = stub_socket();
+    let mut target = SessionTarget::new();
+
+    let started_at = Instant::now();
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(end, ConnectionEnd::Stalled);
+    assert_eq!(started_at.elapsed(), Duration::from_secs(15));
+
+    assert!(matches!(
+        &statuses(&mut status)[..],
+        [EventSubStatus::Stalled { timeout: 10_000 }]
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn keepalives_keep_connections_alive() {
+    let (mut status, client) = client();
+    let (incoming_tx, mut socket) = stub_socket();
+    let mut target = SessionTarget::new();
+
+    incoming_tx.send(welcome("first", Some(10))).unwrap();
+
+    tokio::spawn(async move {
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_secs(14)).await;
+            incoming_tx.send(keepalive()).unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_secs(14)).await;
+        incoming_tx.send(Ok(Message::Close(None))).unwrap();
+    });
+
+    let started_at = Instant::now();
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(
+        end,
+        ConnectionEnd::Closed(String::from("Connection closed"))
+    );
+    assert_eq!(started_at.elapsed(), Duration::from_secs(56));
+
+    assert!(
+        !statuses(&mut status)
+            .iter()
+            .any(|status| matches!(status, EventSubStatus::Stalled { .. }))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn stalled_reconnects_start_a_new_session() {
+    let (_status, client) = client();
+    let mut target = SessionTarget::new();
+
+    let (incoming_tx, mut socket) = stub_socket();
+    incoming_tx.send(welcome("first", Some(10))).unwrap();
+    incoming_tx.send(reconnect("wss://reconnect")).unwrap();
+
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(end, ConnectionEnd::Reconnect);
+    assert_eq!(target.uri, "wss://reconnect");
+    assert!(target.reconnecting);
+
+    // The connection to the reconnect URL never gets welcomed
+    let (_incoming_tx, mut socket) = stub_socket();
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(end, ConnectionEnd::Stalled);
+
+    target.reset();
+
+    // So the next session isn't resumed, and its subscriptions are created
+    // again
+    assert_eq!(target.uri, ENDPOINTS.eventsub_ws);
+    assert!(!target.welcome());
+}
+
+#[tokio::test(start_paused = true)]
+async fn pings_restart_the_watchdog() {
+    let (mut status, client) = client();
+    let (incoming_tx, mut socket) = stub_socket();
+    let mut target = SessionTarget::new();
+
+    incoming_tx.send(welcome("first", Some(10))).unwrap();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(14)).await;
+        incoming_tx
+            .send(Ok(Message::Ping(Default::default())))
+            .unwrap();
+
+        // Keeps the connection open without sending anything
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        drop(incoming_tx);
+    });
+
+    let started_at = Instant::now();
+    let end = client.read_connection(1, &mut socket, &mut target).await;
+
+    assert_eq!(end, ConnectionEnd::Stalled);
+    assert_eq!(started_at.elapsed(), Duration::from_secs(29));
+
+    assert!(matches!(
+        statuses(&mut status).last(),
+        Some(EventSubStatus::Stalled { timeout: 10_000 })
+    ));
+}
+
+fn rate_limit_headers(remaining: &str, reset: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("Ratelimit-Limit", "800".parse().unwrap());
+    headers.insert("Ratelimit-Remaining", remaining.parse().unwrap());
+    headers.insert("Ratelimit-Reset", reset.parse().unwrap());
+
+    headers
+}
+
+#[test]
+fn rate_limits_are_told_apart_from_full_sessions() {
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+    // Waits until the bucket is refilled
+    assert_eq!(
+        rate_limit_delay(
+            StatusCode::TOO_MANY_REQUESTS,
+            &rate_limit_headers("0", "1700000030"),
+            now
+        ),
+        Some(Duration::from_secs(30) + RATE_LIMIT_MARGIN)
+    );
+
+    // A reset that already passed is retried right away
+    assert_eq!(
+        rate_limit_delay(
+            StatusCode::TOO_MANY_REQUESTS,
+            &rate_limit_headers("0", "1699999990"),
+            now
+        ),
+        Some(RATE_LIMIT_MARGIN)
+    );
+
+    // A full session or used up cost still leave requests
+    assert_eq!(
+        rate_limit_delay(
+            StatusCode::TOO_MANY_REQUESTS,
+            &rate_limit_headers("799", "1700000030"),
+            now
+        ),
+        None
+    );
+    assert_eq!(
+        rate_limit_delay(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), now),
+        None
+    );
+
+    assert_eq!(
+        rate_limit_delay(
+            StatusCode::CONFLICT,
+            &rate_limit_headers("0", "1700000030"),
+            SystemTime::now()
+        ),
+        None
+    );
+}
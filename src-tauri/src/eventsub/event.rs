@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests;
+
+use serde::de::DeserializeOwned;
+use twitch_api::eventsub::EventType;
+use twitch_api::eventsub::automod::{AutomodMessageHoldV2Payload, AutomodMessageUpdateV2Payload};
+use twitch_api::eventsub::channel::{
+    ChannelChatUserMessageHoldV1Payload, ChannelChatUserMessageUpdateV1Payload,
+    ChannelModerateV2Payload, ChannelSubscriptionEndV1Payload,
+    ChannelSuspiciousUserMessageV1Payload, ChannelSuspiciousUserUpdateV1Payload,
+    ChannelUnbanRequestCreateV1Payload, ChannelUnbanRequestResolveV1Payload,
+    ChannelWarningAcknowledgeV1Payload,
+};
+use twitch_api::eventsub::stream::{StreamOfflineV1Payload, StreamOnlineV1Payload};
+use twitch_api::eventsub::user::UserUpdateV1Payload;
+
+/// Event of a notification, parsed according to the type and version of the
+/// subscription that delivered it.
+///
+/// Only read on the Rust side. The payload types don't serialize back into
+/// the shape Twitch sent, e.g. `channel.moderate` loses the fields of the
+/// other actions, so the webview receives the event as it arrived.
+#[derive(Debug, Clone)]
+pub enum Event {
+    AutomodMessageHold(Box<AutomodMessageHoldV2Payload>),
+    AutomodMessageUpdate(Box<AutomodMessageUpdateV2Payload>),
+    ChannelChatUserMessageHold(Box<ChannelChatUserMessageHoldV1Payload>),
+    ChannelChatUserMessageUpdate(Box<ChannelChatUserMessageUpdateV1Payload>),
+    ChannelModerate(Box<ChannelModerateV2Payload>),
+    ChannelSubscriptionEnd(Box<ChannelSubscriptionEndV1Payload>),
+    ChannelSuspiciousUserMessage(Box<ChannelSuspiciousUserMessageV1Payload>),
+    ChannelSuspiciousUserUpdate(Box<ChannelSuspiciousUserUpdateV1Payload>),
+    ChannelUnbanRequestCreate(Box<ChannelUnbanRequestCreateV1Payload>),
+    ChannelUnbanRequestResolve(Box<ChannelUnbanRequestResolveV1Payload>),
+    ChannelWarningAcknowledge(Box<ChannelWarningAcknowledgeV1Payload>),
+    StreamOffline(Box<StreamOfflineV1Payload>),
+    StreamOnline(Box<StreamOnlineV1Payload>),
+    UserUpdate(Box<UserUpdateV1Payload>),
+    /// Event of a type or version that isn't parsed, or that didn't match the
+    /// payload of its type.
+    Raw(serde_json::Value),
+}
+
+impl Event {
+    pub fn parse(kind: EventType, version: &str, event: &serde_json::Value) -> Self {
+        use EventType as Ev;
+
+        match (kind, version) {
+            (Ev::AutomodMessageHold, "2") => parse_as(kind, event, Event::AutomodMessageHold),
+            (Ev::AutomodMessageUpdate, "2") => parse_as(kind, event, Event::AutomodMessageUpdate),
+            (Ev::ChannelChatUserMessageHold, "1") => {
+                parse_as(kind, event, Event::ChannelChatUserMessageHold)
+            }
+            (Ev::ChannelChatUserMessageUpdate, "1") => {
+                parse_as(kind, event, Event::ChannelChatUserMessageUpdate)
+            }
+            (Ev::ChannelModerate, "2") => parse_as(kind, event, Event::ChannelModerate),
+            (Ev::ChannelSubscriptionEnd, "1") => {
+                parse_as(kind, event, Event::ChannelSubscriptionEnd)
+            }
+            (Ev::ChannelSuspiciousUserMessage, "1") => {
+                parse_as(kind, event, Event::ChannelSuspiciousUserMessage)
+            }
+            (Ev::ChannelSuspiciousUserUpdate, "1") => {
+                parse_as(kind, event, Event::ChannelSuspiciousUserUpdate)
+            }
+            (Ev::ChannelUnbanRequestCreate, "1") => {
+                parse_as(kind, event, Event::ChannelUnbanRequestCreate)
+            }
+            (Ev::ChannelUnbanRequestResolve, "1") => {
+                parse_as(kind, event, Event::ChannelUnbanRequestResolve)
+            }
+            (Ev::ChannelWarningAcknowledge, "1") => {
+                parse_as(kind, event, Event::ChannelWarningAcknowledge)
+            }
+            (Ev::StreamOffline, "1") => parse_as(kind, event, Event::StreamOffline),
+            (Ev::StreamOnline, "1") => parse_as(kind, event, Event::StreamOnline),
+            (Ev::UserUpdate, "1") => parse_as(kind, event, Event::UserUpdate),
+            _ => Event::Raw(event.clone()),
+        }
+    }
+
+    /// Login of the channel the event happened in. `user.update` isn't tied
+    /// to a channel.
+    pub fn broadcaster_login(&self) -> Option<&str> {
+        use Event as E;
+
+        let login = match self {
+            E::AutomodMessageHold(payload) => &payload.broadcaster_user_login,
+            E::AutomodMessageUpdate(payload) => &payload.broadcaster_user_login,
+            E::ChannelChatUserMessageHold(payload) => &payload.broadcaster_user_login,
+            E::ChannelChatUserMessageUpdate(payload) => &payload.broadcaster_user_login,
+            E::ChannelModerate(payload) => &payload.broadcaster_user_login,
+            E::ChannelSubscriptionEnd(payload) => &payload.broadcaster_user_login,
+            E::ChannelSuspiciousUserMessage(payload) => &payload.broadcaster_user_login,
+            E::ChannelSuspiciousUserUpdate(payload) => &payload.broadcaster_user_login,
+            E::ChannelUnbanRequestCreate(payload) => &payload.broadcaster_user_login,
+            E::ChannelUnbanRequestResolve(payload) => &payload.broadcaster_user_login,
+            E::ChannelWarningAcknowledge(payload) => &payload.broadcaster_user_login,
+            E::StreamOffline(payload) => &payload.broadcaster_user_login,
+            E::StreamOnline(payload) => &payload.broadcaster_user_login,
+            E::UserUpdate(_) => return None,
+            E::Raw(event) => return event.get("broadcaster_user_login")?.as_str(),
+        };
+
+        Some(login.as_str())
+    }
+}
+
+fn parse_as<T>(kind: EventType, event: &serde_json::Value, variant: fn(Box<T>) -> Event) -> Event
+where
+    T: DeserializeOwned,
+{
+    match T::deserialize(event) {
+        Ok(payload) => variant(Box::new(payload)),
+        // Twitch may add fields or values the payload types don't know yet,
+        // the event is still useful to the webview
+        Err(err) => {
+            tracing::warn!(%err, "Failed to parse {kind} event, forwarding it as is");
+            Event::Raw(event.clone())
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use serde_json::json;
+use twitch_api::eventsub::EventType;
+
+use super::Event;
+use crate::eventsub::client::{NotificationPayload, WebSocketMessage};
+
+fn stream_online() -> serde_json::Value {
+    json!({
+        "id": "9001",
+        "broadcaster_user_id": "1337",
+        "broadcaster_user_login": "cool_user",
+        "broadcaster_user_name": "Cool_User",
+        "type": "live",
+        "started_at": "2020-10-11T10:11:12.123Z"
+    })
+}
+
+#[test]
+fn known_events_are_parsed() {
+    let event = Event::parse(EventType::StreamOnline, "1", &stream_online());
+
+    let Event::StreamOnline(payload) = &event else {
+        panic!("expected stream.online, got {event:?}");
+    };
+
+    assert_eq!(payload.broadcaster_user_login.as_str(), "cool_user");
+}
+
+fn notification(kind: EventType, version: &str, event: serde_json::Value) -> serde_json::Value {
+    json!({
+        "metadata": {
+            "message_id": "befa7b53-d79d-478f-86b9-120f112b044e",
+            "message_type": "notification",
+            "message_timestamp": "2022-11-16T10:11:12.464757833Z",
+            "subscription_type": kind,
+            "subscription_version": version
+        },
+        "payload": {
+            "subscription": {
+                "id": "f1c2a387-161a-49f9-a165-0f21d7a4e1c4",
+                "status": "enabled",
+                "type": kind,
+                "version": version,
+                "cost": 0,
+                "condition": { "broadcaster_user_id": "1337" },
+                "transport": { "method": "websocket", "session_id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB" },
+                "created_at": "2022-11-16T10:11:12.464757833Z"
+            },
+            "event": event
+        }
+    })
+}
+
+fn fixture(json: &str) -> serde_json::Value {
+    serde_json::from_str(json).expect("fixture is valid JSON")
+}
+
+#[test]
+fn typed_events_keep_their_shape() {
+    for (kind, version, event) in [
+        (EventType::StreamOnline, "1", stream_online()),
+        (
+            EventType::ChannelModerate,
+            "2",
+            fixture(include_str!("fixtures/channel_moderate_slow.json")),
+        ),
+        (
+            EventType::AutomodMessageHold,
+            "2",
+            fixture(include_str!("fixtures/automod_message_hold.json")),
+        ),
+        (
+            EventType::ChannelSuspiciousUserMessage,
+            "1",
+            fixture(include_str!("fixtures/suspicious_user_message.json")),
+        ),
+    ] {
+        let message: WebSocketMessage =
+            serde_json::from_value(notification(kind, version, event.clone())).unwrap();
+
+        let WebSocketMessage::Notification(payload) = message else {
+            panic!("expected a notification");
+        };
+
+        assert!(
+            !matches!(payload.event, Event::Raw(_)),
+            "{kind} wasn't parsed"
+        );
+        assert!(payload.event.broadcaster_login().is_some());
+
+        // Including the null fields of the actions that didn't happen
+        let serialized = serde_json::to_value(&payload).unwrap();
+        assert_eq!(serialized["event"], event, "{kind} changed shape");
+    }
+}
+
+#[test]
+fn unknown_versions_and_malformed_events_are_kept_raw() {
+    let raw = json!({ "broadcaster_user_id": "1337" });
+
+    for (kind, version) in [
+        (EventType::StreamOnline, "1"),
+        (EventType::StreamOnline, "2"),
+        (EventType::ChannelModerate, "1"),
+        (EventType::ChannelFollow, "2"),
+    ] {
+        let event = Event::parse(kind, version, &raw);
+        assert!(matches!(event, Event::Raw(ref value) if *value == raw));
+    }
+}
+
+#[test]
+fn notifications_are_parsed_by_subscription() {
+    let message = notification(EventType::StreamOnline, "1", stream_online());
+    let message: WebSocketMessage = serde_json::from_value(message).unwrap();
+
+    let WebSocketMessage::Notification(NotificationPayload {
+        subscription,
+        raw_event,
+        event,
+    }) = message
+    else {
+        panic!("expected a notification");
+    };
+
+    assert_eq!(subscription.kind, EventType::StreamOnline);
+    assert_eq!(raw_event, stream_online());
+    assert!(matches!(event, Event::StreamOnline(_)));
+}
+
+#[test]
+fn events_tell_their_channel() {
+    let event = Event::parse(EventType::StreamOnline, "1", &stream_online());
+    assert_eq!(event.broadcaster_login(), Some("cool_user"));
+
+    // Unparsed events are read as far as they go
+    let raw = json!({ "broadcaster_user_login": "forsen" });
+    let event = Event::parse(EventType::ChannelFollow, "2", &raw);
+    assert_eq!(event.broadcaster_login(), Some("forsen"));
+
+    let event = Event::parse(EventType::ChannelFollow, "2", &json!({}));
+    assert_eq!(event.broadcaster_login(), None);
+}
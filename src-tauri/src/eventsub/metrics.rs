@@ -0,0 +1,28 @@
+use serde::Serialize;
+use twitch_api::eventsub::EventType;
+
+/// Usage of the EventSub sessions and the subscriptions that couldn't be
+/// created.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventSubMetrics {
+    pub sessions: Vec<SessionMetrics>,
+    /// Cost of all enabled subscriptions, as last reported by Twitch.
+    pub total_cost: u64,
+    /// Cost Twitch allows in total, known once a subscription was created.
+    pub max_total_cost: Option<u64>,
+    pub failed: Vec<FailedSubscription>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionMetrics {
+    /// Id of the session, if it's currently started.
+    pub session_id: Option<String>,
+    pub subscriptions: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedSubscription {
+    pub channel: Option<String>,
+    pub event: EventType,
+    pub error: String,
+}
@@ -1,15 +1,20 @@
 pub mod client;
+mod event;
+mod metrics;
+mod registry;
+mod status;
 
 use std::sync::Arc;
 
 pub use client::EventSubClient;
 use client::NotificationPayload;
+pub use metrics::EventSubMetrics;
+pub use status::EventSubStatus;
 use tauri::async_runtime::{self, Mutex};
 use tauri::ipc::Channel;
 use tauri::{AppHandle, Manager, State};
 
 use crate::AppState;
-use crate::api::get_access_token;
 use crate::error::Error;
 
 #[tauri::command]
@@ -17,9 +22,14 @@ pub async fn connect_eventsub(
     app_handle: AppHandle,
     state: State<'_, Mutex<AppState>>,
     channel: Channel<NotificationPayload>,
+    status_channel: Channel<EventSubStatus>,
 ) -> Result<(), Error> {
     let mut guard = state.lock().await;
-    let token = get_access_token(&guard)?.clone();
+
+    let Some(token) = guard.token.clone() else {
+        tracing::info!("No access token set, skipping EventSub connection");
+        return Ok(());
+    };
     let helix = Arc::new(guard.helix.clone());
 
     if let Some(client) = &guard.eventsub
@@ -28,10 +38,14 @@ pub async fn connect_eventsub(
         return Ok(());
     }
 
-    let (mut incoming, client) = EventSubClient::new(helix, Arc::new(token));
+    let (mut incoming, mut status, client) = EventSubClient::new(helix, Arc::new(token));
     let client = Arc::new(client);
 
-    guard.eventsub = Some(client.clone());
+    // The old client would otherwise keep its sessions and reconnect them
+    if let Some(old) = guard.eventsub.replace(client.clone()) {
+        old.shutdown();
+    }
+
     drop(guard);
 
     async_runtime::spawn(async move {
@@ -39,17 +53,47 @@ pub async fn connect_eventsub(
             let state = app_handle.state::<Mutex<AppState>>();
             let mut state = state.lock().await;
 
-            state.eventsub = None;
+            if state
+                .eventsub
+                .as_ref()
+                .is_some_and(|eventsub| Arc::ptr_eq(eventsub, &client))
+            {
+                state.eventsub = None;
+            }
         }
 
         Ok::<_, Error>(())
     });
 
+    async_runtime::spawn(async move {
+        while let Some(status) = status.recv().await {
+            tracing::debug!(?status, "EventSub connection status changed");
+
+            if let Err(err) = status_channel.send(status) {
+                tracing::warn!(%err, "Failed to send EventSub status");
+            }
+        }
+    });
+
     async_runtime::spawn(async move {
         while let Some(message) = incoming.recv().await {
-            channel.send(message).unwrap();
+            if let Err(err) = channel.send(message) {
+                tracing::warn!(%err, "Failed to forward EventSub notification");
+            }
         }
     });
 
     Ok(())
 }
+
+#[tracing::instrument(skip_all)]
+#[tauri::command]
+pub async fn get_eventsub_metrics(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Option<EventSubMetrics>, Error> {
+    let Some(eventsub) = state.lock().await.eventsub.clone() else {
+        return Ok(None);
+    };
+
+    Ok(Some(eventsub.metrics().await))
+}
@@ -0,0 +1,392 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use twitch_api::eventsub::EventType;
+
+use super::metrics::{EventSubMetrics, FailedSubscription, SessionMetrics};
+
+/// Subscriptions Twitch allows on a single WebSocket session.
+pub const MAX_SUBSCRIPTIONS_PER_SESSION: usize = 300;
+
+/// WebSocket sessions Twitch allows to be open at the same time.
+pub const MAX_SESSIONS: usize = 3;
+
+/// Identifies a subscription by what it listens to, so the same subscription
+/// is only created once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubscriptionKey {
+    /// Login of the channel the subscription was created for. Subscriptions
+    /// that aren't tied to a joined channel, like `user.update`, have none and
+    /// are kept until the client is dropped.
+    pub channel: Option<String>,
+    pub event: EventType,
+    pub version: &'static str,
+    /// Serialized condition. Keys of JSON objects are sorted, so equal
+    /// conditions serialize the same.
+    pub condition: String,
+}
+
+impl SubscriptionKey {
+    pub fn new(
+        channel: Option<&str>,
+        event: EventType,
+        version: &'static str,
+        condition: &serde_json::Value,
+    ) -> Self {
+        Self {
+            channel: channel.map(|c| c.to_owned()),
+            event,
+            version,
+            condition: condition.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    /// The session the subscription was assigned to hasn't started yet, it's
+    /// created once it has.
+    Queued,
+    /// The subscription is being created.
+    Pending,
+    Enabled,
+    /// Twitch revoked the subscription, `reason` is the status it sent along.
+    Revoked {
+        reason: String,
+    },
+    /// Creating the subscription failed.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionEntry {
+    /// Id Twitch assigned to the subscription once it was created.
+    pub id: Option<String>,
+    pub status: SubscriptionStatus,
+    /// Condition the subscription is created with, kept to create it again
+    /// on a new session.
+    pub condition: serde_json::Value,
+    /// Index of the session the subscription counts towards.
+    pub session: Option<usize>,
+    /// Cost Twitch assigned to the subscription once it was created.
+    pub cost: u64,
+}
+
+impl SubscriptionEntry {
+    /// Whether the subscription exists on Twitch's side and has to be deleted
+    /// there as well.
+    pub fn is_active(&self) -> bool {
+        self.id.is_some() && self.status == SubscriptionStatus::Enabled
+    }
+}
+
+/// Where a subscription is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// The subscription is already being created or enabled.
+    Exists,
+    /// Create the subscription on the session with this id.
+    Create(String),
+    /// The session the subscription was assigned to hasn't started yet.
+    Queued,
+    /// The subscription was assigned to a new session, which has to be
+    /// opened at this index.
+    Open(usize),
+    /// All sessions are full.
+    Full,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    /// Id of the session once Twitch started it.
+    id: Option<String>,
+    subscriptions: usize,
+    /// Twitch refused to create more subscriptions on the session before it
+    /// reached the limit.
+    full: bool,
+}
+
+impl Session {
+    fn has_room(&self) -> bool {
+        !self.full && self.subscriptions < MAX_SUBSCRIPTIONS_PER_SESSION
+    }
+}
+
+/// Subscriptions of an EventSub client along with their status, and the
+/// sessions they're spread across.
+#[derive(Debug, Default)]
+pub struct SubscriptionRegistry {
+    subscriptions: HashMap<SubscriptionKey, SubscriptionEntry>,
+    sessions: Vec<Session>,
+    /// Cost of all enabled subscriptions, as last reported by Twitch.
+    total_cost: u64,
+    max_total_cost: Option<u64>,
+}
+
+impl SubscriptionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the subscription as being created and assigns it to the first
+    /// session with room, opening a new one if all of them are full.
+    pub fn start(&mut self, key: SubscriptionKey, condition: serde_json::Value) -> Placement {
+        if let Some(entry) = self.subscriptions.get(&key)
+            && matches!(
+                entry.status,
+                SubscriptionStatus::Queued
+                    | SubscriptionStatus::Pending
+                    | SubscriptionStatus::Enabled
+            )
+        {
+            return Placement::Exists;
+        }
+
+        self.subscriptions.insert(
+            key.clone(),
+            SubscriptionEntry {
+                id: None,
+                status: SubscriptionStatus::Pending,
+                condition,
+                session: None,
+                cost: 0,
+            },
+        );
+
+        self.place(&key)
+    }
+
+    fn place(&mut self, key: &SubscriptionKey) -> Placement {
+        let Some(entry) = self.subscriptions.get_mut(key) else {
+            return Placement::Exists;
+        };
+
+        let (index, placement) = match self.sessions.iter().position(Session::has_room) {
+            Some(index) => match &self.sessions[index].id {
+                Some(id) => (index, Placement::Create(id.clone())),
+                None => (index, Placement::Queued),
+            },
+            None if self.sessions.len() < MAX_SESSIONS => {
+                self.sessions.push(Session::default());
+
+                let index = self.sessions.len() - 1;
+                (index, Placement::Open(index))
+            }
+            None => return Placement::Full,
+        };
+
+        if !matches!(placement, Placement::Create(_)) {
+            entry.status = SubscriptionStatus::Queued;
+        }
+
+        entry.session = Some(index);
+        self.sessions[index].subscriptions += 1;
+
+        placement
+    }
+
+    /// Opens the first session if there is none yet and returns its index.
+    pub fn open(&mut self) -> Option<usize> {
+        if !self.sessions.is_empty() {
+            return None;
+        }
+
+        self.sessions.push(Session::default());
+
+        Some(0)
+    }
+
+    /// Records the id of a created subscription. Returns `false` if the
+    /// subscription was removed while it was being created, in which case it
+    /// should be deleted again.
+    pub fn enable(&mut self, key: &SubscriptionKey, id: String, cost: u64) -> bool {
+        let Some(entry) = self.subscriptions.get_mut(key) else {
+            return false;
+        };
+
+        entry.id = Some(id);
+        entry.status = SubscriptionStatus::Enabled;
+        entry.cost = cost;
+
+        true
+    }
+
+    pub fn set_cost(&mut self, total_cost: u64, max_total_cost: u64) {
+        self.total_cost = total_cost;
+        self.max_total_cost = Some(max_total_cost);
+    }
+
+    /// Whether the subscriptions use up all of the cost Twitch allows, in which
+    /// case no session can take more.
+    pub fn cost_exhausted(&self) -> bool {
+        self.max_total_cost
+            .is_some_and(|max_total_cost| self.total_cost >= max_total_cost)
+    }
+
+    /// Marks the session of a subscription Twitch refused to create as full
+    /// and assigns the subscription to another one.
+    pub fn session_full(&mut self, key: &SubscriptionKey) -> Placement {
+        let Some(entry) = self.subscriptions.get_mut(key) else {
+            return Placement::Exists;
+        };
+
+        if let Some(index) = entry.session {
+            self.sessions[index].full = true;
+        }
+
+        release(&mut self.sessions, &mut self.total_cost, entry);
+
+        self.place(key)
+    }
+
+    /// Records the id of a session that started and returns the subscriptions
+    /// to create on it along with their conditions.
+    ///
+    /// Subscriptions carry over to a session that continues one that asked to
+    /// reconnect, so only queued ones are returned then. A new session gets all
+    /// subscriptions assigned to it, along with failed ones while it has room.
+    /// Revoked subscriptions are left out, creating them again would fail the
+    /// same way.
+    pub fn start_session(
+        &mut self,
+        index: usize,
+        id: String,
+        resumed: bool,
+    ) -> Vec<(SubscriptionKey, serde_json::Value)> {
+        let Some(session) = self.sessions.get_mut(index) else {
+            return vec![];
+        };
+
+        session.id = Some(id);
+
+        if !resumed {
+            session.full = false;
+        }
+
+        let mut restarted = vec![];
+
+        for (key, entry) in &mut self.subscriptions {
+            let restart = match entry.status {
+                SubscriptionStatus::Queued => entry.session == Some(index),
+                SubscriptionStatus::Pending | SubscriptionStatus::Enabled => {
+                    !resumed && entry.session == Some(index)
+                }
+                SubscriptionStatus::Failed { .. } => {
+                    if resumed || !self.sessions[index].has_room() {
+                        false
+                    } else {
+                        entry.session = Some(index);
+                        self.sessions[index].subscriptions += 1;
+
+                        true
+                    }
+                }
+                SubscriptionStatus::Revoked { .. } => false,
+            };
+
+            if restart {
+                entry.id = None;
+                entry.status = SubscriptionStatus::Pending;
+                entry.cost = 0;
+
+                restarted.push((key.clone(), entry.condition.clone()));
+            }
+        }
+
+        restarted
+    }
+
+    /// Forgets the id of a session whose connection closed. Subscriptions
+    /// assigned to it are queued until it started again.
+    pub fn end_session(&mut self, index: usize) {
+        if let Some(session) = self.sessions.get_mut(index) {
+            session.id = None;
+        }
+    }
+
+    pub fn fail(&mut self, key: &SubscriptionKey, error: String) {
+        if let Some(entry) = self.subscriptions.get_mut(key) {
+            entry.status = SubscriptionStatus::Failed { error };
+            release(&mut self.sessions, &mut self.total_cost, entry);
+        }
+    }
+
+    /// Marks the subscription with the given id as revoked and returns its
+    /// key.
+    pub fn revoke(&mut self, id: &str, reason: String) -> Option<&SubscriptionKey> {
+        let (key, entry) = self
+            .subscriptions
+            .iter_mut()
+            .find(|(_, entry)| entry.id.as_deref() == Some(id))?;
+
+        entry.status = SubscriptionStatus::Revoked { reason };
+        release(&mut self.sessions, &mut self.total_cost, entry);
+
+        Some(key)
+    }
+
+    /// Whether any subscriptions were created for the channel.
+    pub fn has_channel(&self, channel: &str) -> bool {
+        self.subscriptions
+            .keys()
+            .any(|key| key.channel.as_deref() == Some(channel))
+    }
+
+    /// Removes the subscriptions created for the channel and returns them.
+    pub fn remove_channel(&mut self, channel: &str) -> Vec<(SubscriptionKey, SubscriptionEntry)> {
+        let mut removed: Vec<_> = self
+            .subscriptions
+            .extract_if(|key, _| key.channel.as_deref() == Some(channel))
+            .collect();
+
+        for (_, entry) in &mut removed {
+            release(&mut self.sessions, &mut self.total_cost, entry);
+        }
+
+        removed
+    }
+
+    pub fn metrics(&self) -> EventSubMetrics {
+        let failed = self
+            .subscriptions
+            .iter()
+            .filter_map(|(key, entry)| match &entry.status {
+                SubscriptionStatus::Failed { error } => Some(FailedSubscription {
+                    channel: key.channel.clone(),
+                    event: key.event,
+                    error: error.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        EventSubMetrics {
+            sessions: self
+                .sessions
+                .iter()
+                .map(|session| SessionMetrics {
+                    session_id: session.id.clone(),
+                    subscriptions: session.subscriptions,
+                })
+                .collect(),
+            total_cost: self.total_cost,
+            max_total_cost: self.max_total_cost,
+            failed,
+        }
+    }
+}
+
+/// Frees the slot and cost a subscription took up, it no longer exists on
+/// Twitch's side.
+fn release(sessions: &mut [Session], total_cost: &mut u64, entry: &mut SubscriptionEntry) {
+    if let Some(index) = entry.session.take() {
+        sessions[index].subscriptions -= 1;
+    }
+
+    *total_cost = total_cost.saturating_sub(entry.cost);
+    entry.cost = 0;
+}
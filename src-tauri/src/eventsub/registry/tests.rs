@@ -0,0 +1,293 @@
+use serde_json::json;
+use twitch_api::eventsub::EventType;
+
+use super::{
+    MAX_SESSIONS, MAX_SUBSCRIPTIONS_PER_SESSION, Placement, SubscriptionKey, SubscriptionRegistry,
+    SubscriptionStatus,
+};
+
+fn condition(channel: Option<&str>) -> serde_json::Value {
+    match channel {
+        Some("forsen") => json!({ "broadcaster_user_id": "22484632" }),
+        Some(_) => json!({ "broadcaster_user_id": "11148817" }),
+        None => json!({ "user_id": "22484632" }),
+    }
+}
+
+fn key(channel: Option<&str>, event: EventType) -> SubscriptionKey {
+    SubscriptionKey::new(channel, event, "1", &condition(channel))
+}
+
+fn start(registry: &mut SubscriptionRegistry, key: &SubscriptionKey) -> Placement {
+    registry.start(key.clone(), condition(key.channel.as_deref()))
+}
+
+fn enabled(registry: &mut SubscriptionRegistry, key: &SubscriptionKey, id: &str) {
+    assert!(matches!(start(registry, key), Placement::Create(_)));
+    assert!(registry.enable(key, id.into(), 0));
+}
+
+/// Registry with a started first session.
+fn registry() -> SubscriptionRegistry {
+    let mut registry = SubscriptionRegistry::new();
+
+    assert_eq!(registry.open(), Some(0));
+    assert!(registry.start_session(0, "first".into(), false).is_empty());
+
+    registry
+}
+
+/// Fills the sessions with subscriptions to as many channels.
+fn fill(registry: &mut SubscriptionRegistry, sessions: usize) {
+    for i in 0..sessions * MAX_SUBSCRIPTIONS_PER_SESSION {
+        let key = key(Some(&format!("channel{i}")), EventType::StreamOnline);
+        start(registry, &key);
+    }
+}
+
+#[test]
+fn leaving_a_channel_only_removes_its_subscriptions() {
+    let mut registry = registry();
+
+    let user = key(None, EventType::UserUpdate);
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    enabled(&mut registry, &user, "1");
+    enabled(&mut registry, &forsen, "2");
+    enabled(&mut registry, &pajlada, "3");
+
+    let removed = registry.remove_channel("forsen");
+
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].0, forsen);
+    assert_eq!(removed[0].1.id.as_deref(), Some("2"));
+
+    assert!(registry.subscriptions.contains_key(&user));
+    assert!(registry.subscriptions.contains_key(&pajlada));
+
+    assert!(!registry.has_channel("forsen"));
+    assert!(registry.has_channel("pajlada"));
+}
+
+#[test]
+fn conditions_are_part_of_the_key() {
+    let mut registry = registry();
+
+    let first = json!({ "broadcaster_user_id": "22484632", "moderator_user_id": "1" });
+    let same = json!({ "moderator_user_id": "1", "broadcaster_user_id": "22484632" });
+    let other = json!({ "broadcaster_user_id": "22484632", "moderator_user_id": "2" });
+
+    for (condition, created) in [(first, true), (same, false), (other, true)] {
+        let key = SubscriptionKey::new(Some("forsen"), EventType::ChannelModerate, "2", &condition);
+        assert_eq!(registry.start(key, condition) != Placement::Exists, created);
+    }
+}
+
+#[test]
+fn revocations_are_matched_by_id() {
+    let mut registry = registry();
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    enabled(&mut registry, &forsen, "1");
+    enabled(&mut registry, &pajlada, "2");
+
+    assert_eq!(
+        registry.revoke("2", "authorization_revoked".into()),
+        Some(&pajlada)
+    );
+    assert_eq!(registry.revoke("3", "user_removed".into()), None);
+
+    assert_eq!(
+        registry.subscriptions[&pajlada].status,
+        SubscriptionStatus::Revoked {
+            reason: "authorization_revoked".into()
+        }
+    );
+    assert!(!registry.subscriptions[&pajlada].is_active());
+    assert!(registry.subscriptions[&forsen].is_active());
+
+    // Revoked subscriptions can be created again
+    assert_eq!(
+        start(&mut registry, &pajlada),
+        Placement::Create("first".into())
+    );
+}
+
+#[test]
+fn subscriptions_removed_while_pending_are_not_enabled() {
+    let mut registry = registry();
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+
+    assert_eq!(
+        start(&mut registry, &forsen),
+        Placement::Create("first".into())
+    );
+    assert_eq!(start(&mut registry, &forsen), Placement::Exists);
+
+    let removed = registry.remove_channel("forsen");
+    assert!(!removed[0].1.is_active());
+
+    assert!(!registry.enable(&forsen, "1".into(), 0));
+}
+
+#[test]
+fn new_sessions_skip_revoked_subscriptions() {
+    let mut registry = registry();
+
+    let user = key(None, EventType::UserUpdate);
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    enabled(&mut registry, &user, "1");
+    enabled(&mut registry, &forsen, "2");
+    enabled(&mut registry, &pajlada, "3");
+    registry.revoke("3", "authorization_revoked".into());
+
+    let mut restarted = registry.start_session(0, "second".into(), false);
+    restarted.sort_by_key(|(key, _)| key.channel.clone());
+
+    assert_eq!(
+        restarted,
+        [
+            (user.clone(), condition(None)),
+            (forsen.clone(), condition(Some("forsen"))),
+        ]
+    );
+
+    for key in [&user, &forsen] {
+        assert_eq!(registry.subscriptions[key].id, None);
+        assert_eq!(
+            registry.subscriptions[key].status,
+            SubscriptionStatus::Pending
+        );
+    }
+
+    // Subscriptions being created again aren't created twice
+    assert_eq!(start(&mut registry, &forsen), Placement::Exists);
+}
+
+#[test]
+fn resumed_sessions_only_create_queued_subscriptions() {
+    let mut registry = registry();
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    enabled(&mut registry, &forsen, "1");
+    registry.end_session(0);
+
+    assert_eq!(start(&mut registry, &pajlada), Placement::Queued);
+
+    assert_eq!(
+        registry.start_session(0, "first".into(), true),
+        [(pajlada.clone(), condition(Some("pajlada")))]
+    );
+
+    assert!(registry.subscriptions[&forsen].is_active());
+    assert_eq!(
+        registry.subscriptions[&pajlada].status,
+        SubscriptionStatus::Pending
+    );
+}
+
+#[test]
+fn full_sessions_open_new_ones() {
+    let mut registry = registry();
+
+    fill(&mut registry, 1);
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    assert_eq!(start(&mut registry, &forsen), Placement::Open(1));
+    assert_eq!(start(&mut registry, &pajlada), Placement::Queued);
+
+    let mut created = registry.start_session(1, "second".into(), false);
+    created.sort_by_key(|(key, _)| key.channel.clone());
+
+    assert_eq!(
+        created,
+        [
+            (forsen.clone(), condition(Some("forsen"))),
+            (pajlada.clone(), condition(Some("pajlada"))),
+        ]
+    );
+
+    assert_eq!(registry.subscriptions[&forsen].session, Some(1));
+
+    // Leaving a channel makes room on its session again
+    registry.remove_channel("channel0");
+
+    let user = key(None, EventType::UserUpdate);
+    assert_eq!(
+        start(&mut registry, &user),
+        Placement::Create("first".into())
+    );
+}
+
+#[test]
+fn subscriptions_fail_once_all_sessions_are_full() {
+    let mut registry = registry();
+
+    fill(&mut registry, MAX_SESSIONS);
+
+    assert_eq!(registry.sessions.len(), MAX_SESSIONS);
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    assert_eq!(start(&mut registry, &forsen), Placement::Full);
+
+    registry.fail(&forsen, "full".into());
+    assert_eq!(registry.subscriptions[&forsen].session, None);
+
+    // Failed subscriptions are tried again once a new session has room
+    registry.remove_channel("channel0");
+
+    assert_eq!(
+        registry.start_session(0, "third".into(), false).len(),
+        MAX_SUBSCRIPTIONS_PER_SESSION
+    );
+    assert_eq!(registry.subscriptions[&forsen].session, Some(0));
+}
+
+#[test]
+fn refused_subscriptions_move_to_another_session() {
+    let mut registry = registry();
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+    let pajlada = key(Some("pajlada"), EventType::StreamOnline);
+
+    enabled(&mut registry, &forsen, "1");
+    assert_eq!(
+        start(&mut registry, &pajlada),
+        Placement::Create("first".into())
+    );
+
+    assert_eq!(registry.session_full(&pajlada), Placement::Open(1));
+    assert_eq!(registry.sessions[0].subscriptions, 1);
+    assert_eq!(registry.subscriptions[&pajlada].session, Some(1));
+}
+
+#[test]
+fn cost_is_released_with_subscriptions() {
+    let mut registry = registry();
+
+    let forsen = key(Some("forsen"), EventType::StreamOnline);
+
+    start(&mut registry, &forsen);
+    assert!(registry.enable(&forsen, "1".into(), 1));
+    registry.set_cost(10, 10);
+
+    assert!(registry.cost_exhausted());
+
+    registry.remove_channel("forsen");
+
+    let metrics = registry.metrics();
+    assert_eq!(metrics.total_cost, 9);
+    assert_eq!(metrics.max_total_cost, Some(10));
+    assert_eq!(metrics.sessions[0].subscriptions, 0);
+    assert!(!registry.cost_exhausted());
+}
@@ -0,0 +1,24 @@
+use serde::Serialize;
+use twitch_api::eventsub::EventType;
+
+/// State changes of the EventSub connection.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum EventSubStatus {
+    /// A session was started. Subscriptions of a previous session are created
+    /// again unless the session continues one that asked to reconnect.
+    Connected { session_id: String },
+    /// No message arrived within the keepalive timeout of `timeout`
+    /// milliseconds. The connection is treated as dead and replaced.
+    Stalled { timeout: u64 },
+    /// The connection was closed and is opened again after `retry_in`
+    /// milliseconds.
+    Disconnected { reason: String, retry_in: u64 },
+    /// A subscription couldn't be created, `channel` is the login it was
+    /// created for.
+    SubscriptionFailed {
+        channel: Option<String>,
+        event: EventType,
+        error: String,
+    },
+}
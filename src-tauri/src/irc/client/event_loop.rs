@@ -1,14 +1,46 @@
-use std::collections::VecDeque;
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use tokio::sync::{mpsc, oneshot};
 
 use super::pool_connection::PoolConnection;
-use crate::irc;
-use crate::irc::ClientConfig;
-use crate::irc::connection::event_loop::ConnectionLoopCommand;
+use super::{ConnectionStatus, PoolMetrics};
+use crate::backoff::Backoff;
 use crate::irc::connection::{Connection, ConnectionIncomingMessage};
-use crate::irc::message::{JoinMessage, PartMessage, ServerMessage};
+use crate::irc::message::{
+    ClientMessage, ClientMessageError, JoinMessage, NoticeMessage, PartMessage, ServerMessage,
+    UserStateMessage,
+};
+use crate::irc::{ClientConfig, Error, JoinError, SendError};
+use crate::recent_ids::RecentIds;
+
+/// Number of message ids remembered to deduplicate messages received on both
+/// connections during a handover.
+const RECENT_IDS_CAPACITY: usize = 10_000;
+
+type JoinSender = oneshot::Sender<Result<(), JoinError>>;
+
+type SendSender = oneshot::Sender<Result<Option<String>, SendError>>;
+
+/// A PRIVMSG waiting to be acknowledged or rejected by the server.
+#[derive(Debug)]
+struct PendingSend {
+    nonce: String,
+    connection_id: usize,
+    channel_login: String,
+    return_sender: SendSender,
+}
+
+/// Callers waiting for a JOIN to be confirmed by the server.
+#[derive(Debug, Default)]
+struct PendingJoin {
+    sent_at: Option<Instant>,
+    waiters: Vec<JoinSender>,
+}
 
 #[derive(Debug)]
 pub(crate) enum ClientLoopCommand {
@@ -17,14 +49,40 @@ pub(crate) enum ClientLoopCommand {
     },
     Join {
         channel_login: String,
+        return_sender: JoinSender,
+    },
+    JoinTimeout {
+        channel_login: String,
     },
-    Part {
+    ProcessJoinQueue,
+    Privmsg {
         channel_login: String,
+        message: String,
+        reply_parent_id: Option<String>,
+        return_sender: SendSender,
+    },
+    SendFailed {
+        nonce: String,
+        cause: Error,
+    },
+    SendTimeout {
+        nonce: String,
+    },
+    Send {
+        message: ClientMessage,
+        return_sender: oneshot::Sender<Result<(), SendError>>,
+    },
+    Reconnect,
+    FinishHandover {
+        connection_id: usize,
     },
     IncomingMessage {
         source_connection_id: usize,
         message: Box<ConnectionIncomingMessage>,
     },
+    GetMetrics {
+        return_sender: oneshot::Sender<PoolMetrics>,
+    },
 }
 
 pub(crate) struct ClientLoopWorker {
@@ -35,6 +93,29 @@ pub(crate) struct ClientLoopWorker {
     connections: VecDeque<PoolConnection>,
     client_loop_tx: Weak<mpsc::UnboundedSender<ClientLoopCommand>>,
     client_incoming_messages_tx: mpsc::UnboundedSender<ServerMessage>,
+    client_status_tx: mpsc::UnboundedSender<ConnectionStatus>,
+    reconnect_backoff: Backoff,
+    reconnect_at: Option<Instant>,
+    /// Set once the server refused the token or the capabilities, which it
+    /// does again on every retry. No connections are opened afterwards and
+    /// everything sent through the client fails with it.
+    permanent_failure: Option<Error>,
+    /// Channels of closed connections waiting to be joined again once the
+    /// reconnect backoff has elapsed.
+    pending_rejoins: HashSet<String>,
+    /// Replacement connections that are joining the channels of a connection
+    /// that received a RECONNECT, mapped to the id of that connection.
+    handovers: HashMap<usize, usize>,
+    recent_ids: RecentIds,
+    shared_chat_ids: RecentIds,
+    /// Channels waiting for the JOIN rate limit to allow sending their JOIN.
+    join_queue: VecDeque<String>,
+    join_send_times: VecDeque<Instant>,
+    join_queue_scheduled: bool,
+    pending_joins: HashMap<String, PendingJoin>,
+    reconnects: u64,
+    /// Sent PRIVMSGs in the order they were sent.
+    pending_sends: VecDeque<PendingSend>,
 }
 
 impl ClientLoopWorker {
@@ -43,8 +124,40 @@ impl ClientLoopWorker {
         client_loop_tx: Weak<mpsc::UnboundedSender<ClientLoopCommand>>,
         client_loop_rx: mpsc::UnboundedReceiver<ClientLoopCommand>,
         client_incoming_messages_tx: mpsc::UnboundedSender<ServerMessage>,
+        client_status_tx: mpsc::UnboundedSender<ConnectionStatus>,
     ) {
-        let worker = ClientLoopWorker {
+        let worker = ClientLoopWorker::new(
+            config,
+            client_loop_tx,
+            client_loop_rx,
+            client_incoming_messages_tx,
+            client_status_tx,
+        );
+
+        tokio::spawn(worker.run());
+    }
+
+    fn new(
+        config: Arc<ClientConfig>,
+        client_loop_tx: Weak<mpsc::UnboundedSender<ClientLoopCommand>>,
+        client_loop_rx: mpsc::UnboundedReceiver<ClientLoopCommand>,
+        client_incoming_messages_tx: mpsc::UnboundedSender<ServerMessage>,
+        client_status_tx: mpsc::UnboundedSender<ConnectionStatus>,
+    ) -> ClientLoopWorker {
+        ClientLoopWorker {
+            reconnect_backoff: Backoff::new(config.reconnect_backoff),
+            reconnect_at: None,
+            permanent_failure: None,
+            pending_rejoins: HashSet::new(),
+            handovers: HashMap::new(),
+            recent_ids: RecentIds::new(RECENT_IDS_CAPACITY),
+            shared_chat_ids: RecentIds::new(RECENT_IDS_CAPACITY),
+            join_queue: VecDeque::new(),
+            join_send_times: VecDeque::new(),
+            join_queue_scheduled: false,
+            pending_joins: HashMap::new(),
+            reconnects: 0,
+            pending_sends: VecDeque::new(),
             config,
             next_connection_id: 0,
             current_whisper_connection_id: None,
@@ -52,9 +165,8 @@ impl ClientLoopWorker {
             connections: VecDeque::new(),
             client_loop_tx,
             client_incoming_messages_tx,
-        };
-
-        tokio::spawn(worker.run());
+            client_status_tx,
+        }
     }
 
     async fn run(mut self) {
@@ -66,19 +178,54 @@ impl ClientLoopWorker {
     fn process_command(&mut self, command: ClientLoopCommand) {
         match command {
             ClientLoopCommand::Connect { return_sender } => {
-                if self.connections.is_empty() {
+                if self.connections.is_empty() && self.permanent_failure.is_none() {
                     let new_connection = self.make_new_connection();
                     self.connections.push_back(new_connection);
                 }
 
                 return_sender.send(()).ok();
             }
-            ClientLoopCommand::Join { channel_login } => self.join(channel_login),
-            ClientLoopCommand::Part { channel_login } => self.part(channel_login),
+            ClientLoopCommand::Join {
+                channel_login,
+                return_sender,
+            } => self.join(channel_login, Some(return_sender)),
+            ClientLoopCommand::JoinTimeout { channel_login } => self.on_join_timeout(channel_login),
+            ClientLoopCommand::ProcessJoinQueue => {
+                self.join_queue_scheduled = false;
+                self.process_join_queue();
+            }
+            ClientLoopCommand::Privmsg {
+                channel_login,
+                message,
+                reply_parent_id,
+                return_sender,
+            } => self.privmsg(channel_login, message, reply_parent_id, return_sender),
+            ClientLoopCommand::SendFailed { nonce, cause } => {
+                self.resolve_send(|s| s.nonce == nonce, Err(SendError::Connection(cause)));
+            }
+            ClientLoopCommand::SendTimeout { nonce } => {
+                self.resolve_send(|s| s.nonce == nonce, Err(SendError::Timeout));
+            }
+            ClientLoopCommand::Send {
+                message,
+                return_sender,
+            } => self.send(message, return_sender),
+            ClientLoopCommand::Reconnect => self.reconnect(),
+            ClientLoopCommand::FinishHandover { connection_id } => {
+                self.on_handover_timeout(connection_id)
+            }
             ClientLoopCommand::IncomingMessage {
                 source_connection_id,
                 message,
             } => self.on_incoming_message(source_connection_id, *message),
+            ClientLoopCommand::GetMetrics { return_sender } => {
+                let metrics = PoolMetrics {
+                    reconnects: self.reconnects,
+                    connections: self.connections.iter_mut().map(|c| c.metrics()).collect(),
+                };
+
+                return_sender.send(metrics).ok();
+            }
         }
     }
 
@@ -105,9 +252,15 @@ impl ClientLoopWorker {
             rx_kill_incoming,
         ));
 
+        self.send_status(ConnectionStatus::Initializing { connection_id });
+
         pool_conn
     }
 
+    fn send_status(&self, status: ConnectionStatus) {
+        self.client_status_tx.send(status).ok();
+    }
+
     async fn run_incoming_forward_task(
         mut connection_incoming_messages_rx: mpsc::UnboundedReceiver<ConnectionIncomingMessage>,
         connection_id: usize,
@@ -120,16 +273,20 @@ impl ClientLoopWorker {
                     break;
                 }
                 incoming_message = connection_incoming_messages_rx.recv() => {
-                    if let Some(incoming_message) = incoming_message {
-                        if let Some(client_loop_tx) = client_loop_tx.upgrade() {
-                            client_loop_tx.send(ClientLoopCommand::IncomingMessage {
-                                source_connection_id: connection_id,
-                                message: Box::new(incoming_message)
-                            }).unwrap();
-                        } else {
-                            break;
-                        }
-                    } else {
+                    let Some(incoming_message) = incoming_message else {
+                        break;
+                    };
+
+                    let Some(client_loop_tx) = client_loop_tx.upgrade() else {
+                        break;
+                    };
+
+                    let sent = client_loop_tx.send(ClientLoopCommand::IncomingMessage {
+                        source_connection_id: connection_id,
+                        message: Box::new(incoming_message)
+                    });
+
+                    if sent.is_err() {
                         break;
                     }
                 }
@@ -137,15 +294,94 @@ impl ClientLoopWorker {
         }
     }
 
-    fn join(&mut self, channel_login: String) {
+    fn join(&mut self, channel_login: String, return_sender: Option<JoinSender>) {
+        if let Some(cause) = &self.permanent_failure {
+            if let Some(return_sender) = return_sender {
+                return_sender
+                    .send(Err(JoinError::Connection(cause.clone())))
+                    .ok();
+            }
+
+            return;
+        }
+
+        self.pending_rejoins.remove(&channel_login);
+
         let channel_already_confirmed_joined = self.connections.iter().any(|c| {
             c.wanted_channels.contains(&channel_login) && c.server_channels.contains(&channel_login)
         });
 
         if channel_already_confirmed_joined {
+            if let Some(return_sender) = return_sender {
+                return_sender.send(Ok(())).ok();
+            }
+
             return;
         }
 
+        if let Some(return_sender) = return_sender {
+            self.pending_joins
+                .entry(channel_login.clone())
+                .or_default()
+                .waiters
+                .push(return_sender);
+        }
+
+        if !self.join_queue.contains(&channel_login) {
+            self.join_queue.push_back(channel_login);
+        }
+
+        self.process_join_queue();
+    }
+
+    /// Sends as many queued JOINs as the rate limit allows and schedules the
+    /// rest for when the oldest JOIN leaves the window.
+    fn process_join_queue(&mut self) {
+        let now = Instant::now();
+
+        while let Some(&sent_at) = self.join_send_times.front()
+            && now.duration_since(sent_at) >= self.config.join_window
+        {
+            self.join_send_times.pop_front();
+        }
+
+        while self.join_send_times.len() < self.config.max_joins_per_window
+            && let Some(channel_login) = self.join_queue.pop_front()
+        {
+            self.send_join(channel_login);
+            self.join_send_times.push_back(now);
+        }
+
+        if self.join_queue.is_empty() || self.join_queue_scheduled {
+            return;
+        }
+
+        let Some(&oldest) = self.join_send_times.front() else {
+            return;
+        };
+
+        self.join_queue_scheduled = true;
+
+        let delay = (oldest + self.config.join_window).saturating_duration_since(now);
+        let client_loop_tx = self.client_loop_tx.clone();
+
+        tracing::debug!(
+            queued = self.join_queue.len(),
+            "JOIN rate limit reached, sending next in {delay:?}"
+        );
+
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                client_loop_tx
+                    .send(ClientLoopCommand::ProcessJoinQueue)
+                    .ok();
+            }
+        });
+    }
+
+    fn send_join(&mut self, channel_login: String) {
         let mut pool_connection = self
             .connections
             .iter()
@@ -153,57 +389,322 @@ impl ClientLoopWorker {
             .or_else(|| {
                 self.connections
                     .iter()
-                    .position(|c| c.channels_limit_not_reached())
+                    .position(|c| !c.retiring && c.channels_limit_not_reached())
             })
-            .map(|pos| self.connections.remove(pos).unwrap())
+            .and_then(|pos| self.connections.remove(pos))
             .unwrap_or_else(|| self.make_new_connection());
 
-        pool_connection
-            .connection
-            .connection_loop_tx
-            .send(ConnectionLoopCommand::SendMessage(
-                irc!["JOIN", format!("#{}", channel_login)],
-                None,
-            ))
-            .unwrap();
+        // The channel stays wanted, so it's joined again once the closed
+        // connection is replaced
+        let join = ClientMessage::Join {
+            channel_login: channel_login.clone(),
+        };
 
-        pool_connection.register_sent_message();
-        pool_connection.wanted_channels.insert(channel_login);
+        if let Err(err) = pool_connection.send_message(join, None) {
+            tracing::warn!(%err, "Failed to send JOIN for {channel_login}");
+        }
+
+        pool_connection
+            .wanted_channels
+            .insert(channel_login.clone());
 
         self.connections.push_back(pool_connection);
+
+        if let Some(pending_join) = self.pending_joins.get_mut(&channel_login) {
+            pending_join.sent_at = Some(Instant::now());
+
+            let join_timeout = self.config.join_timeout;
+            let client_loop_tx = self.client_loop_tx.clone();
+
+            tokio::spawn(async move {
+                tokio::time::sleep(join_timeout).await;
+
+                if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                    client_loop_tx
+                        .send(ClientLoopCommand::JoinTimeout { channel_login })
+                        .ok();
+                }
+            });
+        }
     }
 
-    fn part(&mut self, channel_login: String) {
-        if self
-            .connections
-            .iter()
-            .all(|c| !c.wanted_channels.contains(&channel_login))
-        {
+    fn on_join_timeout(&mut self, channel_login: String) {
+        // Timers of earlier attempts to join the same channel are ignored
+        let timed_out = self.pending_joins.get(&channel_login).is_some_and(|p| {
+            p.sent_at
+                .is_some_and(|sent_at| sent_at.elapsed() >= self.config.join_timeout)
+        });
+
+        if timed_out {
+            tracing::warn!("Timed out joining {channel_login}");
+            self.resolve_join(&channel_login, Err(JoinError::Timeout));
+        }
+    }
+
+    fn resolve_join(&mut self, channel_login: &str, result: Result<(), JoinError>) {
+        if let Some(pending_join) = self.pending_joins.remove(channel_login) {
+            for waiter in pending_join.waiters {
+                waiter.send(result.clone()).ok();
+            }
+        }
+    }
+
+    /// Sends a message tagged with a `client-nonce` on the connection that
+    /// joined the channel, so the server's answer can be traced back to it.
+    fn privmsg(
+        &mut self,
+        channel_login: String,
+        message: String,
+        reply_parent_id: Option<String>,
+        return_sender: SendSender,
+    ) {
+        if let Some(cause) = &self.permanent_failure {
+            return_sender
+                .send(Err(SendError::Connection(cause.clone())))
+                .ok();
             return;
         }
 
-        let mut pool_connection = self
+        let Some(pool_connection) = self
             .connections
+            .iter_mut()
+            .filter(|c| c.server_channels.contains(&channel_login))
+            .min_by_key(|c| c.retiring)
+        else {
+            return_sender
+                .send(Err(SendError::NotJoined(channel_login)))
+                .ok();
+            return;
+        };
+
+        let nonce = format!("{:032x}", rand::random::<u128>());
+
+        let privmsg = ClientMessage::Privmsg {
+            channel_login: channel_login.clone(),
+            message,
+            reply_parent_id,
+            client_nonce: Some(nonce.clone()),
+        };
+
+        if let Err(err) = privmsg.validate() {
+            return_sender.send(Err(SendError::Invalid(err))).ok();
+            return;
+        }
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        if let Err(err) = pool_connection.send_message(privmsg, Some(reply_tx)) {
+            return_sender.send(Err(SendError::Connection(err))).ok();
+            return;
+        }
+
+        self.pending_sends.push_back(PendingSend {
+            nonce: nonce.clone(),
+            connection_id: pool_connection.id,
+            channel_login,
+            return_sender,
+        });
+
+        let send_timeout = self.config.send_timeout;
+        let client_loop_tx = self.client_loop_tx.clone();
+
+        tokio::spawn(async move {
+            if let Ok(Err(cause)) = reply_rx.await {
+                if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                    client_loop_tx
+                        .send(ClientLoopCommand::SendFailed { nonce, cause })
+                        .ok();
+                }
+
+                return;
+            }
+
+            tokio::time::sleep(send_timeout).await;
+
+            if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                client_loop_tx
+                    .send(ClientLoopCommand::SendTimeout { nonce })
+                    .ok();
+            }
+        });
+    }
+
+    /// Sends a message as is and resolves once it was written to the
+    /// connection. Messages to a channel are sent on the connection that
+    /// joined it, any other message on every connection of the pool.
+    ///
+    /// Joins resolve once the server confirmed or refused them, like those of
+    /// [`ClientLoopCommand::Join`].
+    fn send(
+        &mut self,
+        message: ClientMessage,
+        return_sender: oneshot::Sender<Result<(), SendError>>,
+    ) {
+        if let Some(cause) = &self.permanent_failure {
+            return_sender
+                .send(Err(SendError::Connection(cause.clone())))
+                .ok();
+            return;
+        }
+
+        let connections: Vec<_> = match &message {
+            // Joins and parts go through the pool, so it knows which
+            // connection is in which channel
+            ClientMessage::Join { channel_login } => {
+                let (join_tx, join_rx) = oneshot::channel();
+                self.join(channel_login.clone(), Some(join_tx));
+
+                tokio::spawn(async move {
+                    let result = join_rx.await.unwrap_or(Err(JoinError::Cancelled));
+                    return_sender.send(result.map_err(SendError::from)).ok();
+                });
+
+                return;
+            }
+            ClientMessage::Part { channel_login } => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                self.part(channel_login.clone(), reply_tx);
+
+                tokio::spawn(async move {
+                    let result = reply_rx.await.unwrap_or(Err(Error::ConnectionClosed));
+                    return_sender
+                        .send(result.map_err(SendError::Connection))
+                        .ok();
+                });
+
+                return;
+            }
+            // Only meaningful while a connection logs in, which it does on its
+            // own
+            ClientMessage::Pass { .. }
+            | ClientMessage::Nick { .. }
+            | ClientMessage::CapReq { .. } => {
+                return_sender
+                    .send(Err(SendError::Invalid(
+                        ClientMessageError::RegistrationOnly(message.command()),
+                    )))
+                    .ok();
+                return;
+            }
+            message => match message.channel_login() {
+                Some(channel_login) => self
+                    .connections
+                    .iter_mut()
+                    .filter(|c| c.server_channels.contains(channel_login))
+                    .min_by_key(|c| c.retiring)
+                    .into_iter()
+                    .collect(),
+                None => self.connections.iter_mut().collect(),
+            },
+        };
+
+        if connections.is_empty() {
+            let err = match message.channel_login() {
+                Some(channel_login) => SendError::NotJoined(channel_login.to_owned()),
+                None => SendError::Connection(Error::ConnectionClosed),
+            };
+
+            return_sender.send(Err(err)).ok();
+            return;
+        }
+
+        let mut replies = Vec::with_capacity(connections.len());
+
+        for pool_connection in connections {
+            let (reply_tx, reply_rx) = oneshot::channel();
+
+            if let Err(err) = pool_connection.send_message(message.clone(), Some(reply_tx)) {
+                return_sender.send(Err(SendError::Connection(err))).ok();
+                return;
+            }
+
+            replies.push(reply_rx);
+        }
+
+        tokio::spawn(async move {
+            for reply_rx in replies {
+                if let Err(err) = reply_rx.await.unwrap_or(Err(Error::ConnectionClosed)) {
+                    return_sender.send(Err(SendError::Connection(err))).ok();
+                    return;
+                }
+            }
+
+            return_sender.send(Ok(())).ok();
+        });
+    }
+
+    /// Resolves the oldest pending send matching the predicate.
+    fn resolve_send(
+        &mut self,
+        predicate: impl Fn(&PendingSend) -> bool,
+        result: Result<Option<String>, SendError>,
+    ) {
+        if let Some(pending_send) = self
+            .pending_sends
             .iter()
-            .position(|c| c.wanted_channels.contains(&channel_login))
-            .and_then(|pos| self.connections.remove(pos))
-            .unwrap();
+            .position(predicate)
+            .and_then(|pos| self.pending_sends.remove(pos))
+        {
+            pending_send.return_sender.send(result).ok();
+        }
+    }
+
+    fn fail_sends(&mut self, connection_id: usize, cause: &Error) {
+        let (failed, pending): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.pending_sends)
+            .into_iter()
+            .partition(|s| s.connection_id == connection_id);
+
+        self.pending_sends = pending;
+
+        for pending_send in failed {
+            pending_send
+                .return_sender
+                .send(Err(SendError::Connection(cause.clone())))
+                .ok();
+        }
+    }
+
+    /// Parts the channel. The reply sender resolves once the PART was written,
+    /// or right away if the channel wasn't joined and nothing was sent.
+    fn part(&mut self, channel_login: String, reply_sender: oneshot::Sender<Result<(), Error>>) {
+        let Some(mut pool_connection) = self.take_joined_connection(&channel_login) else {
+            reply_sender.send(Ok(())).ok();
+            return;
+        };
+
+        let part = ClientMessage::Part {
+            channel_login: channel_login.clone(),
+        };
+
+        if let Err(err) = pool_connection.send_message(part, Some(reply_sender)) {
+            tracing::warn!(%err, "Failed to send PART for {channel_login}");
+        }
 
-        pool_connection
-            .connection
-            .connection_loop_tx
-            .send(ConnectionLoopCommand::SendMessage(
-                irc!["PART", format!("#{}", channel_login)],
-                None,
-            ))
-            .unwrap();
-
-        pool_connection.register_sent_message();
         pool_connection.wanted_channels.remove(&channel_login);
 
         self.connections.push_back(pool_connection);
     }
 
+    /// Stops joining the channel and takes the connection that joined it out
+    /// of the pool, if any did.
+    fn take_joined_connection(&mut self, channel_login: &str) -> Option<PoolConnection> {
+        // Dropping the waiters resolves their joins as cancelled
+        self.pending_joins.remove(channel_login);
+
+        if self.pending_rejoins.remove(channel_login) {
+            return None;
+        }
+
+        if let Some(pos) = self.join_queue.iter().position(|c| c == channel_login) {
+            self.join_queue.remove(pos);
+        }
+
+        self.connections
+            .iter()
+            .position(|c| c.wanted_channels.contains(channel_login))
+            .and_then(|pos| self.connections.remove(pos))
+    }
+
     fn on_incoming_message(
         &mut self,
         source_connection_id: usize,
@@ -211,6 +712,30 @@ impl ClientLoopWorker {
     ) {
         match message {
             ConnectionIncomingMessage::IncomingMessage(message) => {
+                // Messages still in flight from a connection that was already
+                // dropped after a handover
+                if self
+                    .connections
+                    .iter()
+                    .all(|c| c.id != source_connection_id)
+                {
+                    return;
+                }
+
+                if !self.handovers.is_empty()
+                    && let Some(message_id) = message.message_id()
+                    && !self.recent_ids.insert(message_id)
+                {
+                    return;
+                }
+
+                if self.config.collapse_shared_chat
+                    && let Some(source_id) = message.shared_chat_source_id()
+                    && !self.shared_chat_ids.insert(source_id)
+                {
+                    return;
+                }
+
                 let is_whisper = matches!(*message, ServerMessage::Whisper(_));
 
                 if is_whisper {
@@ -226,49 +751,446 @@ impl ClientLoopWorker {
 
                 match &*message {
                     ServerMessage::Join(JoinMessage { channel_login, .. }) => {
-                        let conn = self
+                        self.resolve_join(channel_login, Ok(()));
+
+                        let Some(conn) = self
                             .connections
                             .iter_mut()
                             .find(|c| c.id == source_connection_id)
-                            .unwrap();
+                        else {
+                            return;
+                        };
 
                         conn.server_channels.insert(channel_login.clone());
+
+                        if let Some(&old_connection_id) = self.handovers.get(&source_connection_id)
+                        {
+                            let handover_complete =
+                                conn.wanted_channels.is_subset(&conn.server_channels);
+
+                            // The channel was never left, so the JOIN of the
+                            // replacement connection is not forwarded.
+                            let already_joined = self.connections.iter().any(|c| {
+                                c.id == old_connection_id
+                                    && c.server_channels.contains(channel_login)
+                            });
+
+                            if handover_complete {
+                                self.finish_handover(source_connection_id);
+                            }
+
+                            if already_joined {
+                                return;
+                            }
+                        }
                     }
+                    ServerMessage::UserState(UserStateMessage {
+                        channel_login,
+                        message_id,
+                        client_nonce,
+                        ..
+                    }) => match client_nonce {
+                        Some(nonce) => {
+                            self.resolve_send(|s| s.nonce == *nonce, Ok(message_id.clone()));
+                        }
+                        // Acknowledgements without the nonce are matched to
+                        // the oldest message sent in the channel
+                        None if message_id.is_some() => self.resolve_send(
+                            |s| {
+                                s.connection_id == source_connection_id
+                                    && s.channel_login == *channel_login
+                            },
+                            Ok(message_id.clone()),
+                        ),
+                        None => {}
+                    },
                     ServerMessage::Part(PartMessage { channel_login, .. }) => {
-                        let conn = self
+                        if let Some(conn) = self
                             .connections
                             .iter_mut()
                             .find(|c| c.id == source_connection_id)
-                            .unwrap();
+                        {
+                            conn.server_channels.remove(channel_login);
+                        }
+                    }
+                    ServerMessage::Notice(NoticeMessage {
+                        channel_login: Some(channel_login),
+                        message_id: Some(notice_id),
+                        message_text,
+                        ..
+                    }) if notice_id.is_join_failure()
+                        && !self.connections.iter().any(|c| {
+                            c.id == source_connection_id
+                                && c.server_channels.contains(channel_login)
+                        }) =>
+                    {
+                        tracing::warn!(%notice_id, "Failed to join {channel_login}: {message_text}");
+
+                        // Rejoins and joins sent through `send` have no
+                        // waiter, the channel still mustn't be joined again on
+                        // every reconnect
+                        if let Some(conn) = self
+                            .connections
+                            .iter_mut()
+                            .find(|c| c.id == source_connection_id)
+                        {
+                            conn.wanted_channels.remove(channel_login);
+                        }
 
-                        conn.server_channels.remove(channel_login);
+                        self.pending_rejoins.remove(channel_login);
+
+                        self.resolve_join(
+                            channel_login,
+                            Err(JoinError::Rejected {
+                                notice_id: notice_id.clone(),
+                                message: message_text.clone(),
+                            }),
+                        );
+                    }
+                    // Twitch does not echo the nonce in NOTICEs, so the oldest
+                    // message sent in the channel is the one that was rejected
+                    ServerMessage::Notice(NoticeMessage {
+                        channel_login: Some(channel_login),
+                        message_id: Some(notice_id),
+                        message_text,
+                        ..
+                    }) if notice_id.is_send_failure()
+                        && self.pending_sends.iter().any(|s| {
+                            s.connection_id == source_connection_id
+                                && s.channel_login == *channel_login
+                        }) =>
+                    {
+                        tracing::warn!(%notice_id, "Message in {channel_login} rejected: {message_text}");
+
+                        self.resolve_send(
+                            |s| {
+                                s.connection_id == source_connection_id
+                                    && s.channel_login == *channel_login
+                            },
+                            Err(SendError::Rejected {
+                                notice_id: notice_id.clone(),
+                                message: message_text.clone(),
+                            }),
+                        );
                     }
+                    ServerMessage::Reconnect(_) => self.start_handover(source_connection_id),
                     _ => {}
                 }
 
                 self.client_incoming_messages_tx.send(*message).ok();
             }
-            ConnectionIncomingMessage::StateClosed => {
-                let mut pool_connection = self
+            ConnectionIncomingMessage::StateOpen { capabilities } => {
+                if let Some(conn) = self
+                    .connections
+                    .iter()
+                    .find(|c| c.id == source_connection_id)
+                {
+                    self.send_status(ConnectionStatus::Open {
+                        connection_id: source_connection_id,
+                        channels: sorted(conn.wanted_channels.iter().cloned()),
+                        capabilities,
+                    });
+                }
+            }
+            ConnectionIncomingMessage::StateClosed { cause } => {
+                let Some(mut pool_connection) = self
                     .connections
                     .iter()
                     .position(|c| c.id == source_connection_id)
                     .and_then(|pos| self.connections.remove(pos))
-                    .unwrap();
+                else {
+                    return;
+                };
+
+                // Replacement connection failed before taking over, its
+                // channels are rejoined like those of any other closed
+                // connection while the old one keeps receiving until it closes
+                if self.handovers.remove(&source_connection_id).is_some() {
+                    self.clear_recent_ids();
+                }
+
+                if matches!(cause, Error::AuthFailed(_) | Error::CapabilitiesRejected(_)) {
+                    self.connections.push_back(pool_connection);
+                    self.fail_permanently(source_connection_id, cause);
+                    return;
+                }
+
+                if pool_connection.retiring {
+                    self.retire(pool_connection, cause);
+                    return;
+                }
 
-                for channel in pool_connection.wanted_channels.drain() {
-                    self.join(channel);
+                self.fail_sends(source_connection_id, &cause);
+
+                if pool_connection.created_at.elapsed()
+                    >= self.reconnect_backoff.config().reset_after
+                {
+                    self.reconnect_backoff.reset();
                 }
 
+                let channels = sorted(pool_connection.wanted_channels.drain());
+                self.pending_rejoins.extend(channels.iter().cloned());
+
                 if self.current_whisper_connection_id == Some(source_connection_id) {
                     self.current_whisper_connection_id = None;
                 }
 
-                if self.connections.is_empty() {
-                    let new_connection = self.make_new_connection();
-                    self.connections.push_back(new_connection);
-                }
+                tracing::warn!(%cause, "IRC connection {source_connection_id} closed");
+
+                self.reconnects += 1;
+
+                let retry_in = self.schedule_reconnect();
+
+                self.send_status(ConnectionStatus::Closed {
+                    connection_id: source_connection_id,
+                    reason: cause,
+                    channels,
+                    retry_in: retry_in.as_millis() as u64,
+                });
+            }
+        }
+    }
+
+    /// Opens a replacement for a connection that received a RECONNECT and
+    /// joins its channels there. The old connection keeps receiving messages
+    /// until all joins are confirmed or the handover times out.
+    fn start_handover(&mut self, connection_id: usize) {
+        let Some(old_connection) = self
+            .connections
+            .iter_mut()
+            .find(|c| c.id == connection_id && !c.retiring)
+        else {
+            return;
+        };
+
+        old_connection.retiring = true;
+        self.reconnects += 1;
+        let channels = std::mem::take(&mut old_connection.wanted_channels);
+
+        tracing::info!(
+            "IRC connection {connection_id} received RECONNECT, handing over {} channels",
+            channels.len()
+        );
+
+        let mut new_connection = self.make_new_connection();
+        let new_connection_id = new_connection.id;
+
+        // The channels are queued as wanted by the new connection so their
+        // JOINs are sent there once the rate limit allows it
+        for channel_login in &channels {
+            if !self.join_queue.contains(channel_login) {
+                self.join_queue.push_back(channel_login.clone());
             }
         }
+
+        let has_channels = !channels.is_empty();
+        new_connection.wanted_channels = channels;
+
+        self.connections.push_back(new_connection);
+        self.handovers.insert(new_connection_id, connection_id);
+
+        if !has_channels {
+            self.finish_handover(new_connection_id);
+            return;
+        }
+
+        self.process_join_queue();
+        self.schedule_handover_timeout(new_connection_id);
+    }
+
+    fn on_handover_timeout(&mut self, connection_id: usize) {
+        let still_queued = self
+            .connections
+            .iter()
+            .find(|c| c.id == connection_id)
+            .is_some_and(|c| {
+                self.join_queue
+                    .iter()
+                    .any(|ch| c.wanted_channels.contains(ch))
+            });
+
+        // Give the replacement the full timeout once its JOINs went out
+        if still_queued && self.handovers.contains_key(&connection_id) {
+            self.schedule_handover_timeout(connection_id);
+            return;
+        }
+
+        self.finish_handover(connection_id);
     }
+
+    fn schedule_handover_timeout(&self, connection_id: usize) {
+        let handover_timeout = self.config.handover_timeout;
+        let client_loop_tx = self.client_loop_tx.clone();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(handover_timeout).await;
+
+            if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                client_loop_tx
+                    .send(ClientLoopCommand::FinishHandover { connection_id })
+                    .ok();
+            }
+        });
+    }
+
+    /// Drops the connection replaced by the given connection.
+    fn finish_handover(&mut self, connection_id: usize) {
+        let Some(old_connection_id) = self.handovers.remove(&connection_id) else {
+            return;
+        };
+
+        self.clear_recent_ids();
+
+        if let Some(old_connection) = self
+            .connections
+            .iter()
+            .position(|c| c.id == old_connection_id)
+            .and_then(|pos| self.connections.remove(pos))
+        {
+            tracing::info!(
+                "IRC connection {connection_id} took over from connection {old_connection_id}"
+            );
+
+            self.retire(old_connection, Error::ReconnectCmd);
+        }
+    }
+
+    fn retire(&mut self, pool_connection: PoolConnection, cause: Error) {
+        if self.current_whisper_connection_id == Some(pool_connection.id) {
+            self.current_whisper_connection_id = None;
+        }
+
+        self.handovers.retain(|_, old| *old != pool_connection.id);
+        self.fail_sends(pool_connection.id, &cause);
+        self.clear_recent_ids();
+
+        self.send_status(ConnectionStatus::Closed {
+            connection_id: pool_connection.id,
+            reason: cause,
+            channels: Vec::new(),
+            retry_in: 0,
+        });
+    }
+
+    /// Closes every connection after the server refused the token or the
+    /// capabilities, since the others were opened with the same ones. Nothing
+    /// is retried until the client is recreated.
+    fn fail_permanently(&mut self, connection_id: usize, cause: Error) {
+        tracing::error!(%cause, "IRC connection {connection_id} failed to log in");
+
+        let connections = std::mem::take(&mut self.connections);
+        let channels = sorted(
+            connections
+                .iter()
+                .flat_map(|c| c.wanted_channels.iter().cloned())
+                .chain(self.pending_rejoins.drain())
+                .chain(self.join_queue.drain(..))
+                .collect::<HashSet<_>>(),
+        );
+
+        for pool_connection in connections {
+            self.fail_sends(pool_connection.id, &cause);
+        }
+
+        for (_, pending_join) in self.pending_joins.drain() {
+            for waiter in pending_join.waiters {
+                waiter.send(Err(JoinError::Connection(cause.clone()))).ok();
+            }
+        }
+
+        self.handovers.clear();
+        self.current_whisper_connection_id = None;
+        self.reconnect_at = None;
+        self.permanent_failure = Some(cause.clone());
+
+        let status = match cause {
+            Error::CapabilitiesRejected(_) => ConnectionStatus::CapabilitiesRejected {
+                connection_id,
+                reason: cause,
+                channels,
+            },
+            _ => ConnectionStatus::AuthFailed {
+                connection_id,
+                reason: cause,
+                channels,
+            },
+        };
+
+        self.send_status(status);
+    }
+
+    fn clear_recent_ids(&mut self) {
+        if self.handovers.is_empty() {
+            self.recent_ids.clear();
+        }
+    }
+
+    /// Schedules the channels of closed connections to be rejoined and returns
+    /// the time until then.
+    fn schedule_reconnect(&mut self) -> Duration {
+        if let Some(reconnect_at) = self.reconnect_at {
+            return reconnect_at.saturating_duration_since(Instant::now());
+        }
+
+        let delay = self.reconnect_backoff.next_delay();
+        self.reconnect_at = Some(Instant::now() + delay);
+
+        let client_loop_tx = self.client_loop_tx.clone();
+
+        tracing::info!(
+            attempt = self.reconnect_backoff.attempt(),
+            "Reconnecting to IRC in {delay:?}"
+        );
+
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            if let Some(client_loop_tx) = client_loop_tx.upgrade() {
+                client_loop_tx.send(ClientLoopCommand::Reconnect).ok();
+            }
+        });
+
+        delay
+    }
+
+    fn reconnect(&mut self) {
+        self.reconnect_at = None;
+
+        if self.permanent_failure.is_some() {
+            return;
+        }
+
+        let channels = std::mem::take(&mut self.pending_rejoins);
+        let mut rejoining: HashMap<usize, Vec<String>> = HashMap::new();
+
+        for channel in channels {
+            self.join(channel.clone(), None);
+
+            if let Some(conn) = self
+                .connections
+                .iter()
+                .find(|c| c.wanted_channels.contains(&channel))
+            {
+                rejoining.entry(conn.id).or_default().push(channel);
+            }
+        }
+
+        if self.connections.is_empty() {
+            let new_connection = self.make_new_connection();
+            self.connections.push_back(new_connection);
+        }
+
+        for (connection_id, channels) in rejoining {
+            self.send_status(ConnectionStatus::Rejoining {
+                connection_id,
+                channels: sorted(channels),
+            });
+        }
+    }
+}
+
+fn sorted(channels: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut channels: Vec<_> = channels.into_iter().collect();
+    channels.sort_unstable();
+    channels
 }
@@ -0,0 +1,426 @@
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot};
+
+use super::{ClientLoopCommand, ClientLoopWorker};
+use crate::irc::client::ConnectionStatus;
+use crate::irc::client::pool_connection::PoolConnection;
+use crate::irc::connection::event_loop::ConnectionLoopCommand;
+use crate::irc::connection::{Connection, ConnectionIncomingMessage, ConnectionStats};
+use crate::irc::message::{ClientMessage, ClientMessageError, NoticeId, ServerMessage};
+use crate::irc::{ClientConfig, Error, JoinError, SendError};
+
+fn config() -> Arc<ClientConfig> {
+    Arc::new(ClientConfig::anonymous())
+}
+
+/// A pool connection whose connection loop has already shut down.
+fn closed_connection(config: &Arc<ClientConfig>, id: usize) -> PoolConnection {
+    let (connection_loop_tx, _) = mpsc::unbounded_channel();
+
+    let connection = Connection {
+        connection_loop_tx: Arc::new(connection_loop_tx),
+        stats: Arc::new(ConnectionStats::new()),
+    };
+
+    PoolConnection::new(Arc::clone(config), id, connection, oneshot::channel().0)
+}
+
+/// A pool connection whose commands are kept in the returned receiver instead
+/// of being sent.
+fn open_connection(
+    config: &Arc<ClientConfig>,
+    id: usize,
+) -> (
+    PoolConnection,
+    mpsc::UnboundedReceiver<ConnectionLoopCommand>,
+) {
+    let (connection_loop_tx, connection_loop_rx) = mpsc::unbounded_channel();
+
+    let connection = Connection {
+        connection_loop_tx: Arc::new(connection_loop_tx),
+        stats: Arc::new(ConnectionStats::new()),
+    };
+
+    let pool_connection =
+        PoolConnection::new(Arc::clone(config), id, connection, oneshot::channel().0);
+
+    (pool_connection, connection_loop_rx)
+}
+
+/// Creates a worker with a single closed connection whose receivers of
+/// incoming messages and status changes are already gone, like after the
+/// webview was reloaded.
+fn orphaned_worker(
+    client_loop_tx: &Arc<mpsc::UnboundedSender<ClientLoopCommand>>,
+    client_loop_rx: mpsc::UnboundedReceiver<ClientLoopCommand>,
+) -> ClientLoopWorker {
+    let config = config();
+
+    let mut worker = ClientLoopWorker::new(
+        Arc::clone(&config),
+        Arc::downgrade(client_loop_tx),
+        client_loop_rx,
+        mpsc::unbounded_channel().0,
+        mpsc::unbounded_channel().0,
+    );
+
+    worker.connections.push_back(closed_connection(&config, 0));
+    worker.next_connection_id = 1;
+
+    worker
+}
+
+fn incoming(line: &str) -> ClientLoopCommand {
+    let message = ServerMessage::parse(line).expect("line is valid IRC");
+
+    ClientLoopCommand::IncomingMessage {
+        source_connection_id: 0,
+        message: Box::new(ConnectionIncomingMessage::IncomingMessage(Box::new(
+            message,
+        ))),
+    }
+}
+
+#[tokio::test]
+async fn sending_on_closed_connection_fails() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let mut worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    let (join_tx, _join_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Join {
+        channel_login: "forsen".into(),
+        return_sender: join_tx,
+    });
+
+    assert!(worker.connections[0].wanted_channels.contains("forsen"));
+
+    worker.process_command(incoming(
+        ":justinfan12345!justinfan12345@justinfan12345.tmi.twitch.tv JOIN #forsen",
+    ));
+
+    let (privmsg_tx, mut privmsg_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Privmsg {
+        channel_login: "forsen".into(),
+        message: "hello".into(),
+        reply_parent_id: None,
+        return_sender: privmsg_tx,
+    });
+
+    assert!(matches!(
+        privmsg_rx.try_recv(),
+        Ok(Err(SendError::Connection(Error::ConnectionClosed)))
+    ));
+
+    let (part_tx, _part_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Send {
+        message: ClientMessage::Part {
+            channel_login: "forsen".into(),
+        },
+        return_sender: part_tx,
+    });
+
+    assert!(worker.connections[0].wanted_channels.is_empty());
+}
+
+#[tokio::test]
+async fn messages_from_unknown_connections_are_ignored() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let mut worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    for line in [
+        ":justinfan12345!justinfan12345@justinfan12345.tmi.twitch.tv JOIN #forsen",
+        ":justinfan12345!justinfan12345@justinfan12345.tmi.twitch.tv PART #forsen",
+    ] {
+        let ClientLoopCommand::IncomingMessage { message, .. } = incoming(line) else {
+            unreachable!();
+        };
+
+        worker.process_command(ClientLoopCommand::IncomingMessage {
+            source_connection_id: 42,
+            message,
+        });
+    }
+
+    assert_eq!(worker.connections.len(), 1);
+    assert!(worker.connections[0].server_channels.is_empty());
+}
+
+#[tokio::test]
+async fn keeps_running_after_receivers_are_dropped() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    tokio::spawn(worker.run());
+
+    for line in [
+        ":justinfan12345!justinfan12345@justinfan12345.tmi.twitch.tv JOIN #forsen",
+        "@badge-info=;badges=;color=;display-name=Forsen;emotes=;first-msg=0;id=1;mod=0;returning-chatter=0;room-id=22484632;subscriber=0;tmi-sent-ts=1726000000000;user-id=22484632 :forsen!forsen@forsen.tmi.twitch.tv PRIVMSG #forsen :hi",
+        ":tmi.twitch.tv 421 justinfan12345 WHO :Unknown command",
+    ] {
+        client_loop_tx.send(incoming(line)).unwrap();
+    }
+
+    let (metrics_tx, metrics_rx) = oneshot::channel();
+    client_loop_tx
+        .send(ClientLoopCommand::GetMetrics {
+            return_sender: metrics_tx,
+        })
+        .unwrap();
+
+    let metrics = metrics_rx.await.expect("client loop is still running");
+    assert_eq!(metrics.connections.len(), 1);
+}
+
+#[tokio::test]
+async fn auth_failure_stops_reconnecting() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let (status_tx, mut status_rx) = mpsc::unbounded_channel();
+    let config = config();
+
+    let mut worker = ClientLoopWorker::new(
+        Arc::clone(&config),
+        Arc::downgrade(&client_loop_tx),
+        client_loop_rx,
+        mpsc::unbounded_channel().0,
+        status_tx,
+    );
+
+    worker.connections.push_back(closed_connection(&config, 0));
+    worker.next_connection_id = 1;
+
+    let (join_tx, mut join_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Join {
+        channel_login: "forsen".into(),
+        return_sender: join_tx,
+    });
+
+    worker.process_command(ClientLoopCommand::IncomingMessage {
+        source_connection_id: 0,
+        message: Box::new(ConnectionIncomingMessage::StateClosed {
+            cause: Error::AuthFailed("Login authentication failed".into()),
+        }),
+    });
+
+    assert!(worker.connections.is_empty());
+    assert!(worker.reconnect_at.is_none());
+    assert!(matches!(
+        join_rx.try_recv(),
+        Ok(Err(JoinError::Connection(Error::AuthFailed(_))))
+    ));
+    assert!(matches!(
+        status_rx.try_recv(),
+        Ok(ConnectionStatus::AuthFailed { channels, .. }) if channels == ["forsen"]
+    ));
+
+    let (connect_tx, _connect_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Connect {
+        return_sender: connect_tx,
+    });
+    worker.process_command(ClientLoopCommand::Reconnect);
+
+    assert!(worker.connections.is_empty());
+
+    let (join_tx, mut join_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Join {
+        channel_login: "pajlada".into(),
+        return_sender: join_tx,
+    });
+
+    assert!(matches!(
+        join_rx.try_recv(),
+        Ok(Err(JoinError::Connection(Error::AuthFailed(_))))
+    ));
+}
+
+#[tokio::test]
+async fn rejected_capabilities_stop_reconnecting() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let (status_tx, mut status_rx) = mpsc::unbounded_channel();
+    let config = config();
+
+    let mut worker = ClientLoopWorker::new(
+        Arc::clone(&config),
+        Arc::downgrade(&client_loop_tx),
+        client_loop_rx,
+        mpsc::unbounded_channel().0,
+        status_tx,
+    );
+
+    worker.connections.push_back(closed_connection(&config, 0));
+    worker.next_connection_id = 1;
+
+    worker.process_command(ClientLoopCommand::Join {
+        channel_login: "forsen".into(),
+        return_sender: oneshot::channel().0,
+    });
+
+    worker.process_command(ClientLoopCommand::IncomingMessage {
+        source_connection_id: 0,
+        message: Box::new(ConnectionIncomingMessage::StateClosed {
+            cause: Error::CapabilitiesRejected(vec!["twitch.tv/commands".into()]),
+        }),
+    });
+
+    assert!(worker.connections.is_empty());
+    assert!(worker.reconnect_at.is_none());
+    assert!(matches!(
+        status_rx.try_recv(),
+        Ok(ConnectionStatus::CapabilitiesRejected { channels, .. }) if channels == ["forsen"]
+    ));
+
+    worker.process_command(ClientLoopCommand::Reconnect);
+
+    assert!(worker.connections.is_empty());
+}
+
+#[tokio::test]
+async fn join_failures_without_waiters_are_not_rejoined() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let mut worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    // Like a rejoin after a reconnect, nobody waits for the JOIN
+    worker.join("forsen".into(), None);
+
+    assert!(worker.connections[0].wanted_channels.contains("forsen"));
+
+    worker.process_command(incoming(
+        "@msg-id=msg_channel_suspended :tmi.twitch.tv NOTICE #forsen :This channel does not exist or has been suspended.",
+    ));
+
+    assert!(worker.connections[0].wanted_channels.is_empty());
+}
+
+#[tokio::test]
+async fn only_send_failures_reject_pending_sends() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let config = config();
+
+    let mut worker = ClientLoopWorker::new(
+        Arc::clone(&config),
+        Arc::downgrade(&client_loop_tx),
+        client_loop_rx,
+        mpsc::unbounded_channel().0,
+        mpsc::unbounded_channel().0,
+    );
+
+    let (mut pool_connection, _connection_loop_rx) = open_connection(&config, 0);
+    pool_connection.server_channels.insert("forsen".into());
+    worker.connections.push_back(pool_connection);
+    worker.next_connection_id = 1;
+
+    let (privmsg_tx, mut privmsg_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Privmsg {
+        channel_login: "forsen".into(),
+        message: "hello".into(),
+        reply_parent_id: None,
+        return_sender: privmsg_tx,
+    });
+
+    assert_eq!(worker.pending_sends.len(), 1);
+
+    worker.process_command(incoming(
+        "@msg-id=slow_on :tmi.twitch.tv NOTICE #forsen :This room is now in slow mode. You may send messages every 30 seconds.",
+    ));
+
+    assert_eq!(worker.pending_sends.len(), 1);
+    assert!(privmsg_rx.try_recv().is_err());
+
+    worker.process_command(incoming(
+        "@msg-id=msg_duplicate :tmi.twitch.tv NOTICE #forsen :Your message is identical to the one you sent less than 30 seconds ago.",
+    ));
+
+    assert!(worker.pending_sends.is_empty());
+    assert!(matches!(
+        privmsg_rx.try_recv(),
+        Ok(Err(SendError::Rejected {
+            notice_id: NoticeId::MsgDuplicate,
+            ..
+        }))
+    ));
+}
+
+#[tokio::test]
+async fn sent_joins_and_parts_report_their_result() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let mut worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    let (join_tx, join_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Send {
+        message: ClientMessage::Join {
+            channel_login: "forsen".into(),
+        },
+        return_sender: join_tx,
+    });
+
+    worker.process_command(incoming(
+        ":justinfan12345!justinfan12345@justinfan12345.tmi.twitch.tv JOIN #forsen",
+    ));
+
+    assert!(matches!(join_rx.await, Ok(Ok(()))));
+
+    // The connection is closed, so the PART can't be written
+    let (part_tx, part_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Send {
+        message: ClientMessage::Part {
+            channel_login: "forsen".into(),
+        },
+        return_sender: part_tx,
+    });
+
+    assert!(matches!(
+        part_rx.await,
+        Ok(Err(SendError::Connection(Error::ConnectionClosed)))
+    ));
+
+    // Nothing is written for a channel that isn't joined
+    let (part_tx, part_rx) = oneshot::channel();
+    worker.process_command(ClientLoopCommand::Send {
+        message: ClientMessage::Part {
+            channel_login: "pajlada".into(),
+        },
+        return_sender: part_tx,
+    });
+
+    assert!(matches!(part_rx.await, Ok(Ok(()))));
+}
+
+#[tokio::test]
+async fn registration_messages_are_not_sent() {
+    let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
+    let client_loop_tx = Arc::new(client_loop_tx);
+    let mut worker = orphaned_worker(&client_loop_tx, client_loop_rx);
+
+    for message in [
+        ClientMessage::Pass {
+            token: "oauth:token".into(),
+        },
+        ClientMessage::Nick {
+            login: "justinfan12345".into(),
+        },
+        ClientMessage::CapReq {
+            capabilities: vec!["twitch.tv/tags".into()],
+        },
+    ] {
+        let command = message.command();
+        let (send_tx, mut send_rx) = oneshot::channel();
+
+        worker.process_command(ClientLoopCommand::Send {
+            message,
+            return_sender: send_tx,
+        });
+
+        assert!(matches!(
+            send_rx.try_recv(),
+            Ok(Err(SendError::Invalid(ClientMessageError::RegistrationOnly(c)))) if c == command
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+/// Health of the connections in the client's pool.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PoolMetrics {
+    /// Number of times a connection was replaced, either after it closed or
+    /// because the server sent RECONNECT.
+    ///
+    /// Counted for the whole pool rather than per connection. A replacement
+    /// gets a new id and the channels of a closed connection may be rejoined
+    /// on different ones, so there is no slot a count per connection would
+    /// stay attached to.
+    pub reconnects: u64,
+    pub connections: Vec<ConnectionMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionMetrics {
+    pub connection_id: usize,
+    pub channels: Vec<String>,
+    /// Round-trip time of the last PING in milliseconds, if one was answered.
+    pub rtt: Option<f64>,
+    pub messages_in: u64,
+    pub messages_out: u64,
+    /// Messages received per second since the previous sample.
+    pub messages_in_rate: f64,
+    /// Messages sent per second since the previous sample.
+    pub messages_out_rate: f64,
+    /// Milliseconds since the connection was established.
+    pub uptime: u64,
+}
@@ -1,13 +1,17 @@
 pub(crate) mod event_loop;
+mod metrics;
 mod pool_connection;
+mod status;
 
 use std::sync::Arc;
 
 use event_loop::{ClientLoopCommand, ClientLoopWorker};
+pub use metrics::PoolMetrics;
+pub use status::ConnectionStatus;
 use tokio::sync::{mpsc, oneshot};
 
-use super::message::ServerMessage;
-use super::ClientConfig;
+use super::message::{ClientMessage, ServerMessage};
+use super::{ClientConfig, JoinError, SendError};
 
 #[derive(Debug, Clone)]
 pub struct IrcClient {
@@ -15,21 +19,33 @@ pub struct IrcClient {
 }
 
 impl IrcClient {
-    pub fn new(config: ClientConfig) -> (mpsc::UnboundedReceiver<ServerMessage>, Self) {
+    pub fn new(
+        config: ClientConfig,
+    ) -> (
+        mpsc::UnboundedReceiver<ServerMessage>,
+        mpsc::UnboundedReceiver<ConnectionStatus>,
+        Self,
+    ) {
         let config = Arc::new(config);
         let (client_loop_tx, client_loop_rx) = mpsc::unbounded_channel();
 
         let client_loop_tx = Arc::new(client_loop_tx);
         let (client_incoming_messages_tx, client_incoming_messages_rx) = mpsc::unbounded_channel();
+        let (client_status_tx, client_status_rx) = mpsc::unbounded_channel();
 
         ClientLoopWorker::spawn(
             config,
             Arc::downgrade(&client_loop_tx),
             client_loop_rx,
             client_incoming_messages_tx,
+            client_status_tx,
         );
 
-        (client_incoming_messages_rx, Self { client_loop_tx })
+        (
+            client_incoming_messages_rx,
+            client_status_rx,
+            Self { client_loop_tx },
+        )
     }
 }
 
@@ -41,20 +57,81 @@ impl IrcClient {
             .send(ClientLoopCommand::Connect {
                 return_sender: return_tx,
             })
-            .unwrap();
+            .ok();
 
-        return_rx.await.unwrap()
+        return_rx.await.ok();
     }
 
-    pub fn join(&self, channel_login: String) {
+    /// Joins the channel once the JOIN rate limit allows it and resolves when
+    /// the server confirms the join.
+    pub async fn join(&self, channel_login: String) -> Result<(), JoinError> {
+        ClientMessage::Join {
+            channel_login: channel_login.clone(),
+        }
+        .validate()
+        .map_err(JoinError::Invalid)?;
+
+        let (return_tx, return_rx) = oneshot::channel();
+
+        self.client_loop_tx
+            .send(ClientLoopCommand::Join {
+                channel_login,
+                return_sender: return_tx,
+            })
+            .ok();
+
+        return_rx.await.unwrap_or(Err(JoinError::Cancelled))
+    }
+
+    /// Sends a message to the channel and resolves with the id of the message
+    /// once the server acknowledges it.
+    pub async fn privmsg(
+        &self,
+        channel_login: String,
+        message: String,
+        reply_parent_id: Option<String>,
+    ) -> Result<Option<String>, SendError> {
+        let (return_tx, return_rx) = oneshot::channel();
+
+        self.client_loop_tx
+            .send(ClientLoopCommand::Privmsg {
+                channel_login,
+                message,
+                reply_parent_id,
+                return_sender: return_tx,
+            })
+            .ok();
+
+        return_rx.await.unwrap_or(Err(SendError::Cancelled))
+    }
+
+    /// Sends a message as is and resolves once it was written to the
+    /// connection, or with the error of the connection that failed to write
+    /// it.
+    pub async fn send(&self, message: ClientMessage) -> Result<(), SendError> {
+        message.validate().map_err(SendError::Invalid)?;
+
+        let (return_tx, return_rx) = oneshot::channel();
+
         self.client_loop_tx
-            .send(ClientLoopCommand::Join { channel_login })
-            .unwrap();
+            .send(ClientLoopCommand::Send {
+                message,
+                return_sender: return_tx,
+            })
+            .ok();
+
+        return_rx.await.unwrap_or(Err(SendError::Cancelled))
     }
 
-    pub fn part(&self, channel_login: String) {
+    pub async fn metrics(&self) -> PoolMetrics {
+        let (return_tx, return_rx) = oneshot::channel();
+
         self.client_loop_tx
-            .send(ClientLoopCommand::Part { channel_login })
-            .unwrap();
+            .send(ClientLoopCommand::GetMetrics {
+                return_sender: return_tx,
+            })
+            .ok();
+
+        return_rx.await.unwrap_or_default()
     }
 }
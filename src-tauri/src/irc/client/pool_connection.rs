@@ -1,11 +1,25 @@
 use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use tokio::sync::oneshot;
 
+use super::metrics::ConnectionMetrics;
 use crate::irc::connection::Connection;
-use crate::irc::ClientConfig;
+use crate::irc::connection::event_loop::ConnectionLoopCommand;
+use crate::irc::message::ClientMessage;
+use crate::irc::{ClientConfig, Error};
+
+/// Rates are only recomputed once at least this much time has passed since the
+/// previous sample, so frequent callers don't get noisy values.
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+struct RateSample {
+    at: Instant,
+    messages_in: u64,
+    messages_out: u64,
+}
 
 pub(crate) struct PoolConnection {
     config: Arc<ClientConfig>,
@@ -15,6 +29,12 @@ pub(crate) struct PoolConnection {
     pub wanted_channels: HashSet<String>,
     pub server_channels: HashSet<String>,
     pub message_send_times: VecDeque<Instant>,
+    pub created_at: Instant,
+    /// Set once the server asked the connection to reconnect. No new channels
+    /// are joined on a retiring connection.
+    pub retiring: bool,
+    last_sample: RateSample,
+    rates: (f64, f64),
 }
 
 impl PoolConnection {
@@ -34,9 +54,37 @@ impl PoolConnection {
             server_channels: HashSet::new(),
             message_send_times: VecDeque::with_capacity(message_send_times_max_entries),
             tx_kill_incoming: Some(tx_kill_incoming),
+            created_at: Instant::now(),
+            retiring: false,
+            last_sample: RateSample {
+                at: Instant::now(),
+                messages_in: 0,
+                messages_out: 0,
+            },
+            rates: (0.0, 0.0),
         }
     }
 
+    /// Hands a message to the connection loop, failing if the loop has
+    /// already shut down.
+    pub fn send_message(
+        &mut self,
+        message: ClientMessage,
+        reply_sender: Option<oneshot::Sender<Result<(), Error>>>,
+    ) -> Result<(), Error> {
+        self.connection
+            .connection_loop_tx
+            .send(ConnectionLoopCommand::SendMessage(
+                message.into(),
+                reply_sender,
+            ))
+            .map_err(|_| Error::ConnectionClosed)?;
+
+        self.register_sent_message();
+
+        Ok(())
+    }
+
     pub fn register_sent_message(&mut self) {
         let max_entries = self.config.max_waiting_messages_per_connection * 2;
 
@@ -47,6 +95,42 @@ impl PoolConnection {
         }
     }
 
+    pub fn metrics(&mut self) -> ConnectionMetrics {
+        let stats = &self.connection.stats;
+
+        let sample = RateSample {
+            at: Instant::now(),
+            messages_in: stats.messages_in(),
+            messages_out: stats.messages_out(),
+        };
+
+        let elapsed = sample.at.duration_since(self.last_sample.at);
+
+        if elapsed >= MIN_SAMPLE_INTERVAL {
+            let secs = elapsed.as_secs_f64();
+
+            self.rates = (
+                (sample.messages_in - self.last_sample.messages_in) as f64 / secs,
+                (sample.messages_out - self.last_sample.messages_out) as f64 / secs,
+            );
+            self.last_sample = sample;
+        }
+
+        let mut channels: Vec<_> = self.wanted_channels.iter().cloned().collect();
+        channels.sort_unstable();
+
+        ConnectionMetrics {
+            connection_id: self.id,
+            channels,
+            rtt: stats.rtt().map(|rtt| rtt.as_secs_f64() * 1000.0),
+            messages_in: sample.messages_in,
+            messages_out: sample.messages_out,
+            messages_in_rate: self.rates.0,
+            messages_out_rate: self.rates.1,
+            uptime: stats.uptime().as_millis() as u64,
+        }
+    }
+
     pub fn channels_limit_not_reached(&self) -> bool {
         let configured_limit = self.config.max_channels_per_connection;
         self.wanted_channels.len() < configured_limit
@@ -55,6 +139,8 @@ impl PoolConnection {
 
 impl Drop for PoolConnection {
     fn drop(&mut self) {
-        self.tx_kill_incoming.take().unwrap().send(()).ok();
+        if let Some(tx_kill_incoming) = self.tx_kill_incoming.take() {
+            tx_kill_incoming.send(()).ok();
+        }
     }
 }
@@ -0,0 +1,46 @@
+use serde::Serialize;
+
+use crate::irc::Error;
+
+/// State changes of the connections in the client's pool.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ConnectionStatus {
+    /// A new connection is being established.
+    Initializing { connection_id: usize },
+    /// The connection was established and is joining its channels.
+    Open {
+        connection_id: usize,
+        channels: Vec<String>,
+        /// Capabilities the server granted.
+        capabilities: Vec<String>,
+    },
+    /// The connection was closed. Its channels are rejoined on another
+    /// connection after `retry_in` milliseconds.
+    Closed {
+        connection_id: usize,
+        reason: Error,
+        channels: Vec<String>,
+        retry_in: u64,
+    },
+    /// The server refused the token. All connections were closed and are not
+    /// reopened until the user logs in again.
+    AuthFailed {
+        connection_id: usize,
+        reason: Error,
+        channels: Vec<String>,
+    },
+    /// The server refused some of the requested capabilities. All connections
+    /// were closed and are not reopened, since it would refuse them again.
+    CapabilitiesRejected {
+        connection_id: usize,
+        reason: Error,
+        channels: Vec<String>,
+    },
+    /// Channels of previously closed connections are being rejoined on the
+    /// connection.
+    Rejoining {
+        connection_id: usize,
+        channels: Vec<String>,
+    },
+}
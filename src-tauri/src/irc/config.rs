@@ -3,27 +3,76 @@ use std::time::Duration;
 
 use tokio::sync::Semaphore;
 
+use super::transport::TransportKind;
+use crate::backoff::BackoffConfig;
+
 #[derive(Debug)]
 pub struct ClientConfig {
     pub login: String,
-    pub token: String,
+    /// OAuth token used to log in. Anonymous connections have none and can
+    /// only read chat.
+    pub token: Option<String>,
+    /// Capabilities requested when connecting. Connections are closed if the
+    /// server refuses any of them.
+    ///
+    /// `twitch.tv/membership` isn't requested by default since nothing uses
+    /// the JOINs and PARTs of other users, which are only noise in large
+    /// channels.
+    pub capabilities: Vec<String>,
     pub max_channels_per_connection: usize,
     pub max_waiting_messages_per_connection: usize,
     pub connection_rate_limiter: Arc<Semaphore>,
     pub new_connection_every: Duration,
     pub connect_timeout: Duration,
+    /// Maximum number of JOINs sent within `join_window` across all
+    /// connections.
+    pub max_joins_per_window: usize,
+    pub join_window: Duration,
+    /// How long the server gets to confirm a JOIN after it was sent.
+    pub join_timeout: Duration,
+    /// How long the server gets to acknowledge a PRIVMSG with a USERSTATE or
+    /// reject it with a NOTICE.
+    pub send_timeout: Duration,
+    pub transport: TransportKind,
+    /// Deliver messages of a Shared Chat session only once instead of once
+    /// for every joined channel of the session.
+    pub collapse_shared_chat: bool,
+    pub reconnect_backoff: BackoffConfig,
+    /// How long a replacement connection opened after a RECONNECT gets to
+    /// confirm its joins before the old connection is dropped regardless.
+    pub handover_timeout: Duration,
 }
 
 impl ClientConfig {
     pub fn new(login: String, token: String) -> ClientConfig {
+        ClientConfig::with_credentials(login, Some(token))
+    }
+
+    /// Creates a config that logs in as a random `justinfan` user, which
+    /// Twitch allows to read chat without a token.
+    pub fn anonymous() -> ClientConfig {
+        let login = format!("justinfan{}", rand::random_range(10_000..100_000));
+        ClientConfig::with_credentials(login, None)
+    }
+
+    fn with_credentials(login: String, token: Option<String>) -> ClientConfig {
         ClientConfig {
             login,
             token,
+            capabilities: vec!["twitch.tv/tags".into(), "twitch.tv/commands".into()],
             max_channels_per_connection: 90,
             max_waiting_messages_per_connection: 5,
             connection_rate_limiter: Arc::new(Semaphore::new(1)),
             new_connection_every: Duration::from_secs(2),
             connect_timeout: Duration::from_secs(20),
+            max_joins_per_window: 20,
+            join_window: Duration::from_secs(10),
+            join_timeout: Duration::from_secs(10),
+            send_timeout: Duration::from_secs(10),
+            transport: TransportKind::default(),
+            collapse_shared_chat: false,
+            reconnect_backoff: BackoffConfig::default(),
+            handover_timeout: Duration::from_secs(10),
         }
     }
 }
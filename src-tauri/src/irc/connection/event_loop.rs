@@ -1,3 +1,6 @@
+#[cfg(test)]
+mod tests;
+
 use std::collections::VecDeque;
 use std::sync::{Arc, Weak};
 
@@ -6,20 +9,18 @@ use enum_dispatch::enum_dispatch;
 use futures::{SinkExt, StreamExt};
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::{Duration, Instant, interval_at};
-use tokio_tungstenite::tungstenite::Error as WsError;
 
-use super::ConnectionIncomingMessage;
-use crate::irc;
-use crate::irc::message::{IrcMessage, ServerMessage};
-use crate::irc::websocket::{Incoming, Outgoing, WsTransport};
+use super::{ConnectionIncomingMessage, ConnectionStats};
+use crate::irc::message::{CapMessage, ClientMessage, IrcMessage, ServerMessage};
+use crate::irc::transport::{AnyTransport, Incoming, Outgoing, TransportError};
 use crate::irc::{ClientConfig, Error};
 
 #[derive(Debug)]
 pub(crate) enum ConnectionLoopCommand {
     SendMessage(IrcMessage, Option<oneshot::Sender<Result<(), Error>>>),
-    TransportInitFinished(Result<(WsTransport, String, String), Error>),
-    SendError(Arc<WsError>),
-    IncomingMessage(Option<Result<IrcMessage, Error>>),
+    TransportInitFinished(Result<(AnyTransport, String, Option<String>), Error>),
+    SendError(Arc<TransportError>),
+    IncomingMessage(Option<Result<Box<ServerMessage>, Error>>),
     SendPing(),
     CheckPong(),
 }
@@ -33,12 +34,12 @@ trait ConnectionLoopStateMethods {
     );
     fn on_transport_init_finished(
         self,
-        init_result: Result<(WsTransport, String, String), Error>,
+        init_result: Result<(AnyTransport, String, Option<String>), Error>,
     ) -> ConnectionLoopState;
-    fn on_send_error(self, error: Arc<WsError>) -> ConnectionLoopState;
+    fn on_send_error(self, error: Arc<TransportError>) -> ConnectionLoopState;
     fn on_incoming_message(
         self,
-        maybe_message: Option<Result<IrcMessage, Error>>,
+        maybe_message: Option<Result<Box<ServerMessage>, Error>>,
     ) -> ConnectionLoopState;
     fn send_ping(&mut self);
     fn check_pong(self) -> ConnectionLoopState;
@@ -62,13 +63,16 @@ impl ConnectionLoopWorker {
         connection_incoming_tx: mpsc::UnboundedSender<ConnectionIncomingMessage>,
         connection_loop_tx: Weak<mpsc::UnboundedSender<ConnectionLoopCommand>>,
         connection_loop_rx: mpsc::UnboundedReceiver<ConnectionLoopCommand>,
+        stats: Arc<ConnectionStats>,
     ) {
         let worker = ConnectionLoopWorker {
             connection_loop_rx,
             state: ConnectionLoopState::Initializing(ConnectionLoopInitializingState {
+                capabilities: config.capabilities.clone(),
                 commands_queue: VecDeque::new(),
                 connection_loop_tx: Weak::clone(&connection_loop_tx),
                 connection_incoming_tx,
+                stats,
             }),
         };
 
@@ -92,7 +96,7 @@ impl ConnectionLoopWorker {
                 .acquire_owned()
                 .await;
 
-            let connect_attempt = WsTransport::new();
+            let connect_attempt = config.transport.connect();
             let timeout = tokio::time::sleep(config.connect_timeout);
 
             let transport = tokio::select! {
@@ -156,9 +160,11 @@ type MessageSender =
     mpsc::UnboundedSender<(IrcMessage, Option<oneshot::Sender<Result<(), Error>>>)>;
 
 struct ConnectionLoopInitializingState {
+    capabilities: Vec<String>,
     commands_queue: CommandQueue,
     connection_loop_tx: Weak<mpsc::UnboundedSender<ConnectionLoopCommand>>,
     connection_incoming_tx: mpsc::UnboundedSender<ConnectionIncomingMessage>,
+    stats: Arc<ConnectionStats>,
 }
 
 impl ConnectionLoopInitializingState {
@@ -170,7 +176,7 @@ impl ConnectionLoopInitializingState {
         }
 
         self.connection_incoming_tx
-            .send(ConnectionIncomingMessage::StateClosed)
+            .send(ConnectionIncomingMessage::StateClosed { cause: err.clone() })
             .ok();
 
         ConnectionLoopState::Closed(ConnectionLoopClosedState {
@@ -189,9 +195,11 @@ impl ConnectionLoopInitializingState {
                     break;
                 }
                 incoming_message = transport_incoming.next() => {
-                    let do_exit = matches!(incoming_message, None | Some(Err(_)));
+                    // A line that isn't valid IRC doesn't affect the lines
+                    // after it, so only transport errors end the connection
+                    let do_exit = matches!(incoming_message, None | Some(Err(Either::Left(_))));
 
-                    let incoming_message = incoming_message.map(|x| x.map_err(|e| match e {
+                    let incoming_message = incoming_message.map(|x| x.map(Box::new).map_err(|e| match e {
                         Either::Left(e) => Error::Incoming(Arc::new(e)),
                         Either::Right(e) => Error::IrcParse(e)
                     }));
@@ -214,10 +222,15 @@ impl ConnectionLoopInitializingState {
         mut transport_outgoing: Outgoing,
         mut messages_rx: MessageReceiver,
         connection_loop_tx: Weak<mpsc::UnboundedSender<ConnectionLoopCommand>>,
+        stats: Arc<ConnectionStats>,
     ) {
         while let Some((message, reply_sender)) = messages_rx.recv().await {
             let res = transport_outgoing.send(message).await.map_err(Arc::new);
 
+            if res.is_ok() {
+                stats.record_outgoing();
+            }
+
             if let Err(ref err) = res
                 && let Some(connection_loop_tx) = connection_loop_tx.upgrade()
             {
@@ -278,12 +291,14 @@ impl ConnectionLoopStateMethods for ConnectionLoopInitializingState {
 
     fn on_transport_init_finished(
         self,
-        init_result: Result<(WsTransport, String, String), Error>,
+        init_result: Result<(AnyTransport, String, Option<String>), Error>,
     ) -> ConnectionLoopState {
         match init_result {
             Ok((transport, login, token)) => {
                 let (transport_incoming, transport_outgoing) = transport.split();
 
+                self.stats.mark_open();
+
                 let (kill_incoming_loop_tx, kill_incoming_loop_rx) = oneshot::channel();
                 tokio::spawn(ConnectionLoopInitializingState::run_incoming_forward_task(
                     transport_incoming,
@@ -296,6 +311,7 @@ impl ConnectionLoopStateMethods for ConnectionLoopInitializingState {
                     transport_outgoing,
                     outgoing_messages_rx,
                     Weak::clone(&self.connection_loop_tx),
+                    Arc::clone(&self.stats),
                 ));
 
                 let (kill_pinger_tx, kill_pinger_rx) = oneshot::channel();
@@ -308,17 +324,26 @@ impl ConnectionLoopStateMethods for ConnectionLoopInitializingState {
                     connection_incoming_tx: self.connection_incoming_tx,
                     outgoing_messages_tx,
                     pong_received: false,
+                    ping_sent_at: None,
+                    stats: self.stats,
                     kill_incoming_loop_tx: Some(kill_incoming_loop_tx),
                     kill_pinger_tx: Some(kill_pinger_tx),
+                    requested_capabilities: self.capabilities.clone(),
                 });
 
-                new_state.send_message(
-                    irc!["CAP", "REQ", "twitch.tv/tags twitch.tv/commands"],
-                    None,
-                );
+                // The connection is reported as open once the server granted
+                // the capabilities
+                let cap_req = ClientMessage::CapReq {
+                    capabilities: self.capabilities,
+                };
 
-                new_state.send_message(irc!["PASS", format!("oauth:{}", token)], None);
-                new_state.send_message(irc!["NICK", login], None);
+                new_state.send_message(cap_req.into(), None);
+
+                if let Some(token) = token {
+                    new_state.send_message(ClientMessage::Pass { token }.into(), None);
+                }
+
+                new_state.send_message(ClientMessage::Nick { login }.into(), None);
 
                 for (message, return_sender) in self.commands_queue.into_iter() {
                     new_state.send_message(message, return_sender);
@@ -330,23 +355,24 @@ impl ConnectionLoopStateMethods for ConnectionLoopInitializingState {
         }
     }
 
-    fn on_send_error(self, error: Arc<WsError>) -> ConnectionLoopState {
+    fn on_send_error(self, error: Arc<TransportError>) -> ConnectionLoopState {
         self.transition_to_closed(Error::Outgoing(error))
     }
 
+    // Messages and pings only start once the transport is initialized
+
     fn on_incoming_message(
         self,
-        _maybe_message: Option<Result<IrcMessage, Error>>,
+        _maybe_message: Option<Result<Box<ServerMessage>, Error>>,
     ) -> ConnectionLoopState {
-        unreachable!("messages cannot come in while initializing")
+        tracing::warn!("Ignoring message received while initializing");
+        ConnectionLoopState::Initializing(self)
     }
 
-    fn send_ping(&mut self) {
-        unreachable!("pinger should not run while initializing")
-    }
+    fn send_ping(&mut self) {}
 
     fn check_pong(self) -> ConnectionLoopState {
-        unreachable!("pinger should not run while initializing")
+        ConnectionLoopState::Initializing(self)
     }
 }
 
@@ -354,14 +380,20 @@ struct ConnectionLoopOpenState {
     connection_incoming_tx: mpsc::UnboundedSender<ConnectionIncomingMessage>,
     outgoing_messages_tx: MessageSender,
     pong_received: bool,
+    ping_sent_at: Option<Instant>,
+    stats: Arc<ConnectionStats>,
     kill_incoming_loop_tx: Option<oneshot::Sender<()>>,
     kill_pinger_tx: Option<oneshot::Sender<()>>,
+    /// Capabilities sent in the CAP REQ, all of which have to be granted.
+    requested_capabilities: Vec<String>,
 }
 
 impl ConnectionLoopOpenState {
     fn transition_to_closed(self, cause: Error) -> ConnectionLoopState {
         self.connection_incoming_tx
-            .send(ConnectionIncomingMessage::StateClosed)
+            .send(ConnectionIncomingMessage::StateClosed {
+                cause: cause.clone(),
+            })
             .ok();
 
         ConnectionLoopState::Closed(ConnectionLoopClosedState {
@@ -372,8 +404,13 @@ impl ConnectionLoopOpenState {
 
 impl Drop for ConnectionLoopOpenState {
     fn drop(&mut self) {
-        self.kill_incoming_loop_tx.take().unwrap().send(()).ok();
-        self.kill_pinger_tx.take().unwrap().send(()).ok();
+        if let Some(kill_incoming_loop_tx) = self.kill_incoming_loop_tx.take() {
+            kill_incoming_loop_tx.send(()).ok();
+        }
+
+        if let Some(kill_pinger_tx) = self.kill_pinger_tx.take() {
+            kill_pinger_tx.send(()).ok();
+        }
     }
 }
 
@@ -388,55 +425,86 @@ impl ConnectionLoopStateMethods for ConnectionLoopOpenState {
 
     fn on_transport_init_finished(
         self,
-        _: Result<(WsTransport, String, String), Error>,
+        _: Result<(AnyTransport, String, Option<String>), Error>,
     ) -> ConnectionLoopState {
-        unreachable!("transport init cannot finish more than once")
+        tracing::warn!("Transport init finished more than once");
+        ConnectionLoopState::Open(self)
     }
 
-    fn on_send_error(self, error: Arc<WsError>) -> ConnectionLoopState {
+    fn on_send_error(self, error: Arc<TransportError>) -> ConnectionLoopState {
         self.transition_to_closed(Error::Outgoing(error))
     }
 
     fn on_incoming_message(
         mut self,
-        maybe_message: Option<Result<IrcMessage, Error>>,
+        maybe_message: Option<Result<Box<ServerMessage>, Error>>,
     ) -> ConnectionLoopState {
         match maybe_message {
             None => self.transition_to_closed(Error::RemoteUnexpectedlyClosedConnection),
+            Some(Err(Error::IrcParse(error))) => {
+                tracing::warn!(%error, "Skipping malformed IRC message");
+                ConnectionLoopState::Open(self)
+            }
             Some(Err(error)) => self.transition_to_closed(error),
-            Some(Ok(irc_message)) => {
-                let server_message = ServerMessage::try_from(irc_message);
+            Some(Ok(server_message)) => {
+                self.stats.record_incoming();
+
+                match server_message.as_ref() {
+                    ServerMessage::Cap(CapMessage {
+                        acknowledged: false,
+                        capabilities,
+                    }) => {
+                        return self.transition_to_closed(Error::CapabilitiesRejected(
+                            capabilities.clone(),
+                        ));
+                    }
+                    ServerMessage::Cap(CapMessage {
+                        acknowledged: true,
+                        capabilities,
+                    }) => {
+                        let missing: Vec<_> = self
+                            .requested_capabilities
+                            .iter()
+                            .filter(|requested| !capabilities.contains(requested))
+                            .cloned()
+                            .collect();
+
+                        if !missing.is_empty() {
+                            return self.transition_to_closed(Error::CapabilitiesRejected(missing));
+                        }
 
-                match server_message {
-                    Ok(server_message) => {
                         self.connection_incoming_tx
-                            .send(ConnectionIncomingMessage::IncomingMessage(Box::new(
-                                server_message.clone(),
-                            )))
+                            .send(ConnectionIncomingMessage::StateOpen {
+                                capabilities: capabilities.clone(),
+                            })
                             .ok();
+                    }
+                    // Retrying with the same token fails the same way
+                    ServerMessage::Notice(notice) if notice.is_login_failure() => {
+                        return self
+                            .transition_to_closed(Error::AuthFailed(notice.message_text.clone()));
+                    }
+                    ServerMessage::Ping(_) => {
+                        let pong = ClientMessage::Pong {
+                            server: "tmi.twitch.tv".into(),
+                        };
 
-                        match &server_message {
-                            ServerMessage::Ping(_) => {
-                                self.send_message(irc!["PONG", "tmi.twitch.tv"], None);
-                            }
-                            ServerMessage::Pong(_) => {
-                                self.pong_received = true;
-                            }
-                            ServerMessage::Reconnect(_) => {
-                                return self.transition_to_closed(Error::ReconnectCmd);
-                            }
-                            _ => {}
-                        }
+                        self.send_message(pong.into(), None);
                     }
-                    Err(parse_error) => {
-                        self.connection_incoming_tx
-                            .send(ConnectionIncomingMessage::IncomingMessage(Box::new(
-                                ServerMessage::new_generic(IrcMessage::from(parse_error)),
-                            )))
-                            .ok();
+                    ServerMessage::Pong(_) => {
+                        self.pong_received = true;
+
+                        if let Some(ping_sent_at) = self.ping_sent_at.take() {
+                            self.stats.record_rtt(ping_sent_at.elapsed());
+                        }
                     }
+                    _ => {}
                 }
 
+                self.connection_incoming_tx
+                    .send(ConnectionIncomingMessage::IncomingMessage(server_message))
+                    .ok();
+
                 ConnectionLoopState::Open(self)
             }
         }
@@ -444,7 +512,12 @@ impl ConnectionLoopStateMethods for ConnectionLoopOpenState {
 
     fn send_ping(&mut self) {
         self.pong_received = false;
-        self.send_message(irc!["PING", "tmi.twitch.tv"], None);
+        self.ping_sent_at = Some(Instant::now());
+        let ping = ClientMessage::Ping {
+            server: "tmi.twitch.tv".into(),
+        };
+
+        self.send_message(ping.into(), None);
     }
 
     fn check_pong(self) -> ConnectionLoopState {
@@ -473,16 +546,19 @@ impl ConnectionLoopStateMethods for ConnectionLoopClosedState {
 
     fn on_transport_init_finished(
         self,
-        _: Result<(WsTransport, String, String), Error>,
+        _: Result<(AnyTransport, String, Option<String>), Error>,
     ) -> ConnectionLoopState {
         ConnectionLoopState::Closed(self)
     }
 
-    fn on_send_error(self, _error: Arc<WsError>) -> ConnectionLoopState {
+    fn on_send_error(self, _error: Arc<TransportError>) -> ConnectionLoopState {
         ConnectionLoopState::Closed(self)
     }
 
-    fn on_incoming_message(self, _: Option<Result<IrcMessage, Error>>) -> ConnectionLoopState {
+    fn on_incoming_message(
+        self,
+        _: Option<Result<Box<ServerMessage>, Error>>,
+    ) -> ConnectionLoopState {
         ConnectionLoopState::Closed(self)
     }
 
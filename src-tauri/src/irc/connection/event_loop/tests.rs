@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot};
+
+use super::{
+    ConnectionLoopCommand, ConnectionLoopOpenState, ConnectionLoopState, ConnectionLoopWorker,
+    MessageReceiver,
+};
+use crate::irc::Error;
+use crate::irc::connection::{ConnectionIncomingMessage, ConnectionStats};
+use crate::irc::message::{IrcMessage, IrcParseError, ServerMessage};
+
+struct Harness {
+    worker: ConnectionLoopWorker,
+    incoming_rx: mpsc::UnboundedReceiver<ConnectionIncomingMessage>,
+    outgoing_rx: MessageReceiver,
+}
+
+/// Builds a worker whose transport is already open, without connecting
+/// anywhere.
+fn open_worker() -> Harness {
+    let (_connection_loop_tx, connection_loop_rx) = mpsc::unbounded_channel();
+    let (connection_incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+    let (outgoing_messages_tx, outgoing_rx) = mpsc::unbounded_channel();
+
+    let worker = ConnectionLoopWorker {
+        connection_loop_rx,
+        state: ConnectionLoopState::Open(ConnectionLoopOpenState {
+            connection_incoming_tx,
+            outgoing_messages_tx,
+            pong_received: false,
+            ping_sent_at: None,
+            stats: Arc::new(ConnectionStats::new()),
+            kill_incoming_loop_tx: Some(oneshot::channel().0),
+            kill_pinger_tx: Some(oneshot::channel().0),
+            requested_capabilities: vec!["twitch.tv/tags".into(), "twitch.tv/commands".into()],
+        }),
+    };
+
+    Harness {
+        worker,
+        incoming_rx,
+        outgoing_rx,
+    }
+}
+
+fn incoming(line: &str) -> ConnectionLoopCommand {
+    let message = ServerMessage::parse(line)
+        .map(Box::new)
+        .map_err(Error::IrcParse);
+    ConnectionLoopCommand::IncomingMessage(Some(message))
+}
+
+fn is_open(worker: &ConnectionLoopWorker) -> bool {
+    matches!(worker.state, ConnectionLoopState::Open(_))
+}
+
+#[tokio::test]
+async fn malformed_lines_are_skipped() {
+    let Harness {
+        mut worker,
+        mut incoming_rx,
+        ..
+    } = open_worker();
+
+    for line in [
+        "@ PRIVMSG #forsen :hi",
+        "PRIV_MSG #forsen",
+        "PRIVMSG #forsen  :hi",
+        ":",
+    ] {
+        worker = worker.process_command(incoming(line));
+        assert!(is_open(&worker), "connection closed after {line:?}");
+    }
+
+    worker = worker.process_command(incoming(":tmi.twitch.tv RECONNECT"));
+    assert!(is_open(&worker));
+
+    assert!(matches!(
+        incoming_rx.try_recv(),
+        Ok(ConnectionIncomingMessage::IncomingMessage(message))
+            if matches!(*message, ServerMessage::Reconnect(_))
+    ));
+}
+
+#[tokio::test]
+async fn dropped_client_does_not_close_connection() {
+    let Harness {
+        mut worker,
+        incoming_rx,
+        mut outgoing_rx,
+    } = open_worker();
+
+    drop(incoming_rx);
+
+    worker = worker.process_command(incoming("PING :tmi.twitch.tv"));
+    assert!(is_open(&worker));
+
+    let (pong, _) = outgoing_rx.try_recv().expect("PING is answered");
+    assert_eq!(pong.command, "PONG");
+}
+
+#[tokio::test]
+async fn dropped_transport_is_not_fatal() {
+    let Harness {
+        mut worker,
+        outgoing_rx,
+        ..
+    } = open_worker();
+
+    drop(outgoing_rx);
+
+    let (reply_tx, _reply_rx) = oneshot::channel();
+    worker = worker.process_command(ConnectionLoopCommand::SendMessage(
+        IrcMessage::new("PRIVMSG".into(), vec!["#forsen".into(), "hi".into()]),
+        Some(reply_tx),
+    ));
+    worker = worker.process_command(ConnectionLoopCommand::SendPing());
+
+    assert!(is_open(&worker));
+}
+
+#[tokio::test]
+async fn unexpected_commands_keep_state() {
+    let Harness { mut worker, .. } = open_worker();
+
+    worker = worker.process_command(ConnectionLoopCommand::TransportInitFinished(Err(
+        Error::ConnectTimeout,
+    )));
+    assert!(is_open(&worker));
+
+    worker = worker.process_command(ConnectionLoopCommand::IncomingMessage(Some(Err(
+        Error::IrcParse(IrcParseError::NewlinesInMessage),
+    ))));
+    assert!(is_open(&worker));
+
+    worker = worker.process_command(ConnectionLoopCommand::IncomingMessage(None));
+    assert!(matches!(worker.state, ConnectionLoopState::Closed(_)));
+}
+
+#[tokio::test]
+async fn granted_capabilities_open_the_connection() {
+    let Harness {
+        mut worker,
+        mut incoming_rx,
+        ..
+    } = open_worker();
+
+    worker = worker.process_command(incoming(
+        ":tmi.twitch.tv CAP * ACK :twitch.tv/tags twitch.tv/commands",
+    ));
+    assert!(is_open(&worker));
+
+    assert!(matches!(
+        incoming_rx.try_recv(),
+        Ok(ConnectionIncomingMessage::StateOpen { capabilities })
+            if capabilities == ["twitch.tv/tags", "twitch.tv/commands"]
+    ));
+}
+
+#[tokio::test]
+async fn refused_capabilities_close_the_connection() {
+    let Harness {
+        mut worker,
+        mut incoming_rx,
+        ..
+    } = open_worker();
+
+    worker = worker.process_command(incoming(":tmi.twitch.tv CAP * NAK :twitch.tv/commands"));
+    assert!(matches!(worker.state, ConnectionLoopState::Closed(_)));
+
+    assert!(matches!(
+        incoming_rx.try_recv(),
+        Ok(ConnectionIncomingMessage::StateClosed {
+            cause: Error::CapabilitiesRejected(capabilities)
+        }) if capabilities == ["twitch.tv/commands"]
+    ));
+}
+
+#[tokio::test]
+async fn partially_granted_capabilities_close_the_connection() {
+    let Harness {
+        mut worker,
+        mut incoming_rx,
+        ..
+    } = open_worker();
+
+    worker = worker.process_command(incoming(":tmi.twitch.tv CAP * ACK :twitch.tv/tags"));
+    assert!(matches!(worker.state, ConnectionLoopState::Closed(_)));
+
+    assert!(matches!(
+        incoming_rx.try_recv(),
+        Ok(ConnectionIncomingMessage::StateClosed {
+            cause: Error::CapabilitiesRejected(capabilities)
+        }) if capabilities == ["twitch.tv/commands"]
+    ));
+}
+
+#[tokio::test]
+async fn login_failure_closes_the_connection() {
+    let Harness {
+        mut worker,
+        mut incoming_rx,
+        ..
+    } = open_worker();
+
+    worker = worker.process_command(incoming(
+        ":tmi.twitch.tv NOTICE * :Login authentication failed",
+    ));
+    assert!(matches!(worker.state, ConnectionLoopState::Closed(_)));
+
+    assert!(matches!(
+        incoming_rx.try_recv(),
+        Ok(ConnectionIncomingMessage::StateClosed {
+            cause: Error::AuthFailed(_)
+        })
+    ));
+}
@@ -1,21 +1,31 @@
 pub mod event_loop;
+mod stats;
 
 use std::sync::Arc;
 
+pub use stats::ConnectionStats;
 use tokio::sync::mpsc;
 
 use super::config::ClientConfig;
 use super::connection::event_loop::{ConnectionLoopCommand, ConnectionLoopWorker};
+use super::error::Error;
 use super::message::commands::ServerMessage;
 
 #[derive(Debug)]
 pub enum ConnectionIncomingMessage {
     IncomingMessage(Box<ServerMessage>),
-    StateClosed,
+    /// The server granted the requested capabilities.
+    StateOpen {
+        capabilities: Vec<String>,
+    },
+    StateClosed {
+        cause: Error,
+    },
 }
 
 pub(crate) struct Connection {
     pub connection_loop_tx: Arc<mpsc::UnboundedSender<ConnectionLoopCommand>>,
+    pub stats: Arc<ConnectionStats>,
 }
 
 impl Connection {
@@ -28,14 +38,22 @@ impl Connection {
         let (connection_loop_tx, connection_loop_rx) = mpsc::unbounded_channel();
         let (connection_incoming_tx, connection_incoming_rx) = mpsc::unbounded_channel();
         let connection_loop_tx = Arc::new(connection_loop_tx);
+        let stats = Arc::new(ConnectionStats::new());
 
         ConnectionLoopWorker::spawn(
             config,
             connection_incoming_tx,
             Arc::downgrade(&connection_loop_tx),
             connection_loop_rx,
+            Arc::clone(&stats),
         );
 
-        (connection_incoming_rx, Connection { connection_loop_tx })
+        (
+            connection_incoming_rx,
+            Connection {
+                connection_loop_tx,
+                stats,
+            },
+        )
     }
 }
@@ -0,0 +1,63 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters of a connection, updated by its connection loop and read by the
+/// client's pool.
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+    /// Round-trip time of the last answered PING in microseconds, `u64::MAX`
+    /// until the first PONG was received.
+    rtt: AtomicU64,
+    opened_at: OnceLock<Instant>,
+}
+
+impl ConnectionStats {
+    pub fn new() -> Self {
+        Self {
+            rtt: AtomicU64::new(u64::MAX),
+            ..Default::default()
+        }
+    }
+
+    pub fn record_incoming(&self) {
+        self.messages_in.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_outgoing(&self) {
+        self.messages_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rtt(&self, rtt: Duration) {
+        self.rtt.store(rtt.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn mark_open(&self) {
+        self.opened_at.set(Instant::now()).ok();
+    }
+
+    pub fn messages_in(&self) -> u64 {
+        self.messages_in.load(Ordering::Relaxed)
+    }
+
+    pub fn messages_out(&self) -> u64 {
+        self.messages_out.load(Ordering::Relaxed)
+    }
+
+    pub fn rtt(&self) -> Option<Duration> {
+        match self.rtt.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    /// Time since the transport was established, zero while still connecting.
+    pub fn uptime(&self) -> Duration {
+        self.opened_at
+            .get()
+            .map(Instant::elapsed)
+            .unwrap_or_default()
+    }
+}
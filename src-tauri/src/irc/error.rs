@@ -1,24 +1,26 @@
 use std::sync::Arc;
 
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 use thiserror::Error;
-use tokio_tungstenite::tungstenite::Error as WsError;
 
-use super::message::IrcParseError;
+use super::message::{ClientMessageError, IrcParseError, NoticeId};
+use super::transport::TransportError;
 
 #[derive(Error, Debug)]
 pub enum Error {
     /// Underlying transport failed to connect
     #[error("Underlying transport failed to connect: {0}")]
-    Connect(Arc<WsError>),
+    Connect(Arc<TransportError>),
     /// Underlying transport failed to connect in time
     #[error("Underlying transport failed to connect: Connect timed out")]
     ConnectTimeout,
     /// Error received from incoming stream of messages
     #[error("Error received from incoming stream of messages: {0}")]
-    Incoming(Arc<WsError>),
+    Incoming(Arc<TransportError>),
     /// Error received while trying to send message(s) out
     #[error("Error received while trying to send message(s) out: {0}")]
-    Outgoing(Arc<WsError>),
+    Outgoing(Arc<TransportError>),
     /// Incoming message was not valid IRC
     #[error("Incoming message was not valid IRC: {0}")]
     IrcParse(IrcParseError),
@@ -31,6 +33,78 @@ pub enum Error {
     /// Remote server unexpectedly closed connection
     #[error("Remote server unexpectedly closed connection")]
     RemoteUnexpectedlyClosedConnection,
+    /// Connection loop shut down before a message could be handed to it
+    #[error("Connection is already closed")]
+    ConnectionClosed,
+    /// Server refused the token, connecting again won't succeed until the
+    /// user logs in again
+    #[error("Login failed: {0}")]
+    AuthFailed(String),
+    /// Server refused the requested capabilities
+    #[error("Server refused capabilities: {}", .0.join(" "))]
+    CapabilitiesRejected(Vec<String>),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum JoinError {
+    /// Server did not confirm the JOIN in time
+    #[error("Server did not confirm joining the channel in time")]
+    Timeout,
+    /// Server refused the JOIN with a NOTICE
+    #[error("{message}")]
+    Rejected {
+        notice_id: NoticeId,
+        message: String,
+    },
+    /// Channel was parted or the client shut down before the JOIN was
+    /// confirmed
+    #[error("Join was cancelled")]
+    Cancelled,
+    /// Connection the JOIN was sent on can't be used anymore
+    #[error("Channel could not be joined: {0}")]
+    Connection(Error),
+    /// Channel login can't be sent in a JOIN
+    #[error("Invalid channel login: {0}")]
+    Invalid(ClientMessageError),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum SendError {
+    /// Channel is not joined, so the server's answer could not be received
+    #[error("Not joined to #{0}")]
+    NotJoined(String),
+    /// Message could not be written to the connection
+    #[error("Message could not be sent: {0}")]
+    Connection(Error),
+    /// Server refused the message with a NOTICE
+    #[error("{message}")]
+    Rejected {
+        notice_id: NoticeId,
+        message: String,
+    },
+    /// Server did not acknowledge the message in time
+    #[error("Server did not acknowledge the message in time")]
+    Timeout,
+    /// Client shut down before the message was acknowledged
+    #[error("Sending was cancelled")]
+    Cancelled,
+    /// Message can't be written as a single IRC line
+    #[error("Invalid message: {0}")]
+    Invalid(ClientMessageError),
+}
+
+impl From<JoinError> for SendError {
+    fn from(err: JoinError) -> Self {
+        match err {
+            JoinError::Timeout => SendError::Timeout,
+            JoinError::Rejected { notice_id, message } => {
+                SendError::Rejected { notice_id, message }
+            }
+            JoinError::Cancelled => SendError::Cancelled,
+            JoinError::Connection(err) => SendError::Connection(err),
+            JoinError::Invalid(err) => SendError::Invalid(err),
+        }
+    }
 }
 
 impl Clone for Error {
@@ -44,6 +118,43 @@ impl Clone for Error {
             Error::ReconnectCmd => Error::ReconnectCmd,
             Error::PingTimeout => Error::PingTimeout,
             Error::RemoteUnexpectedlyClosedConnection => Error::RemoteUnexpectedlyClosedConnection,
+            Error::ConnectionClosed => Error::ConnectionClosed,
+            Error::AuthFailed(message) => Error::AuthFailed(message.clone()),
+            Error::CapabilitiesRejected(capabilities) => {
+                Error::CapabilitiesRejected(capabilities.clone())
+            }
         }
     }
 }
+
+impl Error {
+    /// Returns the name of the variant, used to identify the error on the
+    /// frontend.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Connect(_) => "Connect",
+            Error::ConnectTimeout => "ConnectTimeout",
+            Error::Incoming(_) => "Incoming",
+            Error::Outgoing(_) => "Outgoing",
+            Error::IrcParse(_) => "IrcParse",
+            Error::ReconnectCmd => "ReconnectCmd",
+            Error::PingTimeout => "PingTimeout",
+            Error::RemoteUnexpectedlyClosedConnection => "RemoteUnexpectedlyClosedConnection",
+            Error::ConnectionClosed => "ConnectionClosed",
+            Error::AuthFailed(_) => "AuthFailed",
+            Error::CapabilitiesRejected(_) => "CapabilitiesRejected",
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Error", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
@@ -0,0 +1,199 @@
+use thiserror::Error;
+
+use super::IrcMessage;
+use crate::irc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ClientMessageError {
+    #[error("Newlines are not permitted in IRC messages")]
+    NewlinesInMessage,
+    #[error("Expected {0} to be non-empty and without spaces")]
+    MalformedParameter(&'static str),
+    #[error("{0} can only be sent while logging in")]
+    RegistrationOnly(&'static str),
+}
+
+/// A message sent to the Twitch IRC server.
+///
+/// Tag values are escaped when the message is written out, the text of a
+/// message may contain anything but newlines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientMessage {
+    Privmsg {
+        channel_login: String,
+        message: String,
+        /// Id of the message this one replies to.
+        reply_parent_id: Option<String>,
+        /// Opaque value echoed in the USERSTATE that acknowledges the message.
+        client_nonce: Option<String>,
+    },
+    /// A PRIVMSG shown as an action, like `/me` does.
+    Action {
+        channel_login: String,
+        message: String,
+    },
+    Join {
+        channel_login: String,
+    },
+    Part {
+        channel_login: String,
+    },
+    CapReq {
+        capabilities: Vec<String>,
+    },
+    /// OAuth token used to log in, with or without the `oauth:` prefix.
+    Pass {
+        token: String,
+    },
+    Nick {
+        login: String,
+    },
+    Ping {
+        server: String,
+    },
+    Pong {
+        server: String,
+    },
+}
+
+impl ClientMessage {
+    /// Returns the IRC command the message is written as.
+    pub fn command(&self) -> &'static str {
+        match self {
+            ClientMessage::Privmsg { .. } | ClientMessage::Action { .. } => "PRIVMSG",
+            ClientMessage::Join { .. } => "JOIN",
+            ClientMessage::Part { .. } => "PART",
+            ClientMessage::CapReq { .. } => "CAP",
+            ClientMessage::Pass { .. } => "PASS",
+            ClientMessage::Nick { .. } => "NICK",
+            ClientMessage::Ping { .. } => "PING",
+            ClientMessage::Pong { .. } => "PONG",
+        }
+    }
+
+    /// Returns the channel the message is sent to, if any.
+    pub fn channel_login(&self) -> Option<&str> {
+        match self {
+            ClientMessage::Privmsg { channel_login, .. }
+            | ClientMessage::Action { channel_login, .. }
+            | ClientMessage::Join { channel_login }
+            | ClientMessage::Part { channel_login } => Some(channel_login),
+            _ => None,
+        }
+    }
+
+    /// Checks that the message can be written as a single IRC line with the
+    /// parameters in the right place.
+    pub fn validate(&self) -> Result<(), ClientMessageError> {
+        fn single_word(value: &str, name: &'static str) -> Result<(), ClientMessageError> {
+            if value.is_empty() || value.contains([' ', '\r', '\n']) {
+                Err(ClientMessageError::MalformedParameter(name))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn text(value: &str) -> Result<(), ClientMessageError> {
+            if value.contains(['\r', '\n']) {
+                Err(ClientMessageError::NewlinesInMessage)
+            } else {
+                Ok(())
+            }
+        }
+
+        match self {
+            ClientMessage::Privmsg {
+                channel_login,
+                message,
+                reply_parent_id,
+                client_nonce,
+            } => {
+                single_word(channel_login, "channel login")?;
+                text(message)?;
+
+                // Tag values are escaped, so only newlines need to be rejected
+                if let Some(reply_parent_id) = reply_parent_id {
+                    text(reply_parent_id)?;
+                }
+
+                if let Some(client_nonce) = client_nonce {
+                    text(client_nonce)?;
+                }
+
+                Ok(())
+            }
+            ClientMessage::Action {
+                channel_login,
+                message,
+            } => {
+                single_word(channel_login, "channel login")?;
+                text(message)
+            }
+            ClientMessage::Join { channel_login } | ClientMessage::Part { channel_login } => {
+                single_word(channel_login, "channel login")
+            }
+            ClientMessage::CapReq { capabilities } => capabilities
+                .iter()
+                .try_for_each(|capability| single_word(capability, "capability")),
+            ClientMessage::Pass { token } => single_word(token, "token"),
+            ClientMessage::Nick { login } => single_word(login, "login"),
+            ClientMessage::Ping { server } | ClientMessage::Pong { server } => {
+                single_word(server, "server")
+            }
+        }
+    }
+}
+
+impl From<ClientMessage> for IrcMessage {
+    fn from(message: ClientMessage) -> IrcMessage {
+        match message {
+            ClientMessage::Privmsg {
+                channel_login,
+                message,
+                reply_parent_id,
+                client_nonce,
+            } => {
+                let mut irc_message = irc!["PRIVMSG", format!("#{channel_login}"), message];
+
+                if let Some(client_nonce) = client_nonce {
+                    irc_message
+                        .tags
+                        .0
+                        .insert("client-nonce".into(), client_nonce);
+                }
+
+                if let Some(reply_parent_id) = reply_parent_id {
+                    irc_message
+                        .tags
+                        .0
+                        .insert("reply-parent-msg-id".into(), reply_parent_id);
+                }
+
+                irc_message
+            }
+            ClientMessage::Action {
+                channel_login,
+                message,
+            } => irc![
+                "PRIVMSG",
+                format!("#{channel_login}"),
+                format!("\u{0001}ACTION {message}\u{0001}")
+            ],
+            ClientMessage::Join { channel_login } => irc!["JOIN", format!("#{channel_login}")],
+            ClientMessage::Part { channel_login } => irc!["PART", format!("#{channel_login}")],
+            ClientMessage::CapReq { capabilities } => irc!["CAP", "REQ", capabilities.join(" ")],
+            ClientMessage::Pass { token } => {
+                let token = if token.starts_with("oauth:") {
+                    token
+                } else {
+                    format!("oauth:{token}")
+                };
+
+                irc!["PASS", token]
+            }
+            ClientMessage::Nick { login } => irc!["NICK", login],
+            ClientMessage::Ping { server } => irc!["PING", server],
+            ClientMessage::Pong { server } => irc!["PONG", server],
+        }
+    }
+}
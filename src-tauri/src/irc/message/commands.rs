@@ -1,13 +1,52 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
+use std::fmt;
 use std::str::FromStr;
 use std::time::Duration;
 
 use ServerMessageParseError::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
-use super::prefix::IrcPrefix;
-use super::{AsRawIrc, Badge, BasicUser, Emote, IrcMessage, Reply, ReplyParent, ReplyThread};
+use super::{
+    AsRawIrc, Badge, BasicUser, Emote, IrcMessage, IrcMessageRef, IrcParseError, PaidChat, PowerUp,
+    Reply, ReplyParent, ReplyThread, SharedChatSource,
+};
+
+/// Answer to a `CAP REQ`. Twitch grants or refuses all requested capabilities
+/// at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapMessage {
+    /// Whether the capabilities were granted (`ACK`) or refused (`NAK`).
+    pub acknowledged: bool,
+    pub capabilities: Vec<String>,
+}
+
+impl TryFrom<&IrcMessageRef<'_>> for CapMessage {
+    type Error = ServerMessageParseError;
+
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<CapMessage, ServerMessageParseError> {
+        if source.command != "CAP" {
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
+        }
+
+        let acknowledged = match source.try_get_param(1)? {
+            "ACK" => true,
+            "NAK" => false,
+            // Other subcommands are never sent without requesting them
+            _ => return Err(ServerMessageParseError::MismatchedCommand(source.into())),
+        };
+
+        Ok(CapMessage {
+            acknowledged,
+            capabilities: source
+                .try_get_param(2)?
+                .split_whitespace()
+                .map(|c| c.to_owned())
+                .collect(),
+        })
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClearChatMessage {
@@ -16,7 +55,6 @@ pub struct ClearChatMessage {
     pub action: ClearChatAction,
     pub is_recent: bool,
     pub server_timestamp: u64,
-    pub source: IrcMessage,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -34,12 +72,12 @@ pub enum ClearChatAction {
     },
 }
 
-impl TryFrom<IrcMessage> for ClearChatMessage {
+impl TryFrom<&IrcMessageRef<'_>> for ClearChatMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<ClearChatMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<ClearChatMessage, ServerMessageParseError> {
         if source.command != "CLEARCHAT" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         let action = match source.params.get(1) {
@@ -50,23 +88,23 @@ impl TryFrom<IrcMessage> for ClearChatMessage {
                 let ban_duration = source.try_get_optional_nonempty_tag_value("ban-duration")?;
                 match ban_duration {
                     Some(ban_duration) => {
-                        let ban_duration = u64::from_str(ban_duration).map_err(|_| {
+                        let ban_duration = u64::from_str(&ban_duration).map_err(|_| {
                             ServerMessageParseError::MalformedTagValue(
-                                source.to_owned(),
+                                source.into(),
                                 "ban-duration",
-                                ban_duration.to_owned(),
+                                ban_duration.into_owned(),
                             )
                         })?;
 
                         ClearChatAction::UserTimeout {
-                            user_login: user_login.to_owned(),
-                            user_id: user_id.to_owned(),
+                            user_login: (*user_login).to_owned(),
+                            user_id: user_id.into_owned(),
                             duration: Duration::from_secs(ban_duration),
                         }
                     }
                     None => ClearChatAction::UserBan {
-                        user_login: user_login.to_owned(),
-                        user_id: user_id.to_owned(),
+                        user_login: (*user_login).to_owned(),
+                        user_id: user_id.into_owned(),
                     },
                 }
             }
@@ -75,23 +113,16 @@ impl TryFrom<IrcMessage> for ClearChatMessage {
 
         Ok(ClearChatMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
-            channel_id: source.try_get_nonempty_tag_value("room-id")?.to_owned(),
+            channel_id: source.try_get_nonempty_tag_value("room-id")?.into_owned(),
             action,
             is_recent: source
                 .try_get_optional_bool("historical")?
                 .unwrap_or_default(),
             server_timestamp: source.try_get_timestamp("tmi-sent-ts")?,
-            source,
         })
     }
 }
 
-impl From<ClearChatMessage> for IrcMessage {
-    fn from(msg: ClearChatMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClearMsgMessage {
     pub channel_login: String,
@@ -102,43 +133,37 @@ pub struct ClearMsgMessage {
     pub is_action: bool,
     pub is_recent: bool,
     pub server_timestamp: u64,
-    pub source: IrcMessage,
+    pub shared_chat: Option<SharedChatSource>,
 }
 
-impl TryFrom<IrcMessage> for ClearMsgMessage {
+impl TryFrom<&IrcMessageRef<'_>> for ClearMsgMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<ClearMsgMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<ClearMsgMessage, ServerMessageParseError> {
         if source.command != "CLEARMSG" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         let (message_text, is_action) = source.try_get_message_text()?;
 
         Ok(ClearMsgMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
-            channel_id: source.try_get_nonempty_tag_value("room-id")?.to_owned(),
-            sender_login: source.try_get_nonempty_tag_value("login")?.to_owned(),
+            channel_id: source.try_get_nonempty_tag_value("room-id")?.into_owned(),
+            sender_login: source.try_get_nonempty_tag_value("login")?.into_owned(),
             message_id: source
                 .try_get_nonempty_tag_value("target-msg-id")?
-                .to_owned(),
+                .into_owned(),
             server_timestamp: source.try_get_timestamp("tmi-sent-ts")?,
             message_text: message_text.to_owned(),
             is_action,
             is_recent: source
                 .try_get_optional_bool("historical")?
                 .unwrap_or_default(),
-            source,
+            shared_chat: source.try_get_optional_shared_chat_source()?,
         })
     }
 }
 
-impl From<ClearMsgMessage> for IrcMessage {
-    fn from(msg: ClearMsgMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GlobalUserStateMessage {
     pub user_id: String,
@@ -147,63 +172,210 @@ pub struct GlobalUserStateMessage {
     pub badges: Vec<Badge>,
     pub emote_sets: HashSet<String>,
     pub name_color: String,
-    pub source: IrcMessage,
 }
 
-impl TryFrom<IrcMessage> for GlobalUserStateMessage {
+impl TryFrom<&IrcMessageRef<'_>> for GlobalUserStateMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<GlobalUserStateMessage, ServerMessageParseError> {
+    fn try_from(
+        source: &IrcMessageRef<'_>,
+    ) -> Result<GlobalUserStateMessage, ServerMessageParseError> {
         if source.command != "GLOBALUSERSTATE" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(GlobalUserStateMessage {
-            user_id: source.try_get_nonempty_tag_value("user-id")?.to_owned(),
+            user_id: source.try_get_nonempty_tag_value("user-id")?.into_owned(),
             user_name: source
                 .try_get_nonempty_tag_value("display-name")?
-                .to_owned(),
+                .into_owned(),
             badge_info: source.try_get_badges("badge-info")?,
             badges: source.try_get_badges("badges")?,
             emote_sets: source.try_get_emote_sets("emote-sets")?,
-            name_color: source.try_get_color("color")?.to_owned(),
-            source,
+            name_color: source.try_get_color("color")?.into_owned(),
         })
     }
 }
 
-impl From<GlobalUserStateMessage> for IrcMessage {
-    fn from(msg: GlobalUserStateMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JoinMessage {
     pub channel_login: String,
     pub user_login: String,
-    pub source: IrcMessage,
 }
 
-impl TryFrom<IrcMessage> for JoinMessage {
+impl TryFrom<&IrcMessageRef<'_>> for JoinMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<JoinMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<JoinMessage, ServerMessageParseError> {
         if source.command != "JOIN" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(JoinMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
             user_login: source.try_get_prefix_nickname()?.to_owned(),
-            source,
         })
     }
 }
 
-impl From<JoinMessage> for IrcMessage {
-    fn from(msg: JoinMessage) -> IrcMessage {
-        msg.source
+macro_rules! notice_ids {
+    ($($(#[$meta:meta])* $variant:ident => $id:literal,)*) => {
+        /// The `msg-id` tag of a NOTICE.
+        ///
+        /// Serialized as the id itself, ids that are not known yet are kept in
+        /// [`NoticeId::Unknown`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum NoticeId {
+            $($(#[$meta])* $variant,)*
+            Unknown(String),
+        }
+
+        impl NoticeId {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(NoticeId::$variant => $id,)*
+                    NoticeId::Unknown(id) => id,
+                }
+            }
+        }
+
+        impl From<&str> for NoticeId {
+            fn from(id: &str) -> NoticeId {
+                match id {
+                    $($id => NoticeId::$variant,)*
+                    _ => NoticeId::Unknown(id.to_owned()),
+                }
+            }
+        }
+    };
+}
+
+notice_ids! {
+    // Ban, timeout and unban results
+    AlreadyBanned => "already_banned",
+    BadBanAdmin => "bad_ban_admin",
+    BadBanAnon => "bad_ban_anon",
+    BadBanBroadcaster => "bad_ban_broadcaster",
+    BadBanMod => "bad_ban_mod",
+    BadBanSelf => "bad_ban_self",
+    BadBanStaff => "bad_ban_staff",
+    BanSuccess => "ban_success",
+    BadTimeoutAdmin => "bad_timeout_admin",
+    BadTimeoutAnon => "bad_timeout_anon",
+    BadTimeoutBroadcaster => "bad_timeout_broadcaster",
+    BadTimeoutDuration => "bad_timeout_duration",
+    BadTimeoutMod => "bad_timeout_mod",
+    BadTimeoutSelf => "bad_timeout_self",
+    BadTimeoutStaff => "bad_timeout_staff",
+    TimeoutSuccess => "timeout_success",
+    BadUnbanNoBan => "bad_unban_no_ban",
+    UnbanSuccess => "unban_success",
+    UntimeoutBanned => "untimeout_banned",
+    UntimeoutSuccess => "untimeout_success",
+
+    // Room mode changes
+    EmoteOnlyOn => "emote_only_on",
+    EmoteOnlyOff => "emote_only_off",
+    AlreadyEmoteOnlyOn => "already_emote_only_on",
+    AlreadyEmoteOnlyOff => "already_emote_only_off",
+    FollowersOn => "followers_on",
+    FollowersOnZero => "followers_on_zero",
+    FollowersOff => "followers_off",
+    R9kOn => "r9k_on",
+    R9kOff => "r9k_off",
+    AlreadyR9kOn => "already_r9k_on",
+    AlreadyR9kOff => "already_r9k_off",
+    SlowOn => "slow_on",
+    SlowOff => "slow_off",
+    SubsOn => "subs_on",
+    SubsOff => "subs_off",
+    AlreadySubsOn => "already_subs_on",
+    AlreadySubsOff => "already_subs_off",
+
+    // Rejected messages and joins
+    MsgBanned => "msg_banned",
+    MsgBadCharacters => "msg_bad_characters",
+    MsgChannelBlocked => "msg_channel_blocked",
+    MsgChannelSuspended => "msg_channel_suspended",
+    MsgDuplicate => "msg_duplicate",
+    MsgEmoteOnly => "msg_emoteonly",
+    MsgFollowersOnly => "msg_followersonly",
+    MsgFollowersOnlyFollowed => "msg_followersonly_followed",
+    MsgFollowersOnlyZero => "msg_followersonly_zero",
+    MsgR9k => "msg_r9k",
+    MsgRateLimit => "msg_ratelimit",
+    MsgRejected => "msg_rejected",
+    MsgRejectedMandatory => "msg_rejected_mandatory",
+    MsgRequiresVerifiedPhoneNumber => "msg_requires_verified_phone_number",
+    MsgSlowMode => "msg_slowmode",
+    MsgSubsOnly => "msg_subsonly",
+    MsgSuspended => "msg_suspended",
+    MsgTimedOut => "msg_timedout",
+    MsgVerifiedEmail => "msg_verified_email",
+    TosBan => "tos_ban",
+
+    // Permission and command errors
+    NoPermission => "no_permission",
+    InvalidUser => "invalid_user",
+    UnrecognizedCmd => "unrecognized_cmd",
+}
+
+impl NoticeId {
+    /// Whether the NOTICE was sent instead of confirming a JOIN.
+    pub fn is_join_failure(&self) -> bool {
+        matches!(
+            self,
+            NoticeId::MsgBanned | NoticeId::MsgChannelSuspended | NoticeId::TosBan
+        )
+    }
+
+    /// Whether the NOTICE was sent instead of delivering a PRIVMSG. Room mode
+    /// changes and command results are sent on their own and don't mean a
+    /// message was dropped.
+    pub fn is_send_failure(&self) -> bool {
+        match self {
+            NoticeId::MsgBanned
+            | NoticeId::MsgBadCharacters
+            | NoticeId::MsgChannelBlocked
+            | NoticeId::MsgChannelSuspended
+            | NoticeId::MsgDuplicate
+            | NoticeId::MsgEmoteOnly
+            | NoticeId::MsgFollowersOnly
+            | NoticeId::MsgFollowersOnlyFollowed
+            | NoticeId::MsgFollowersOnlyZero
+            | NoticeId::MsgR9k
+            | NoticeId::MsgRateLimit
+            | NoticeId::MsgRejected
+            | NoticeId::MsgRejectedMandatory
+            | NoticeId::MsgRequiresVerifiedPhoneNumber
+            | NoticeId::MsgSlowMode
+            | NoticeId::MsgSubsOnly
+            | NoticeId::MsgSuspended
+            | NoticeId::MsgTimedOut
+            | NoticeId::MsgVerifiedEmail => true,
+            // Twitch prefixes all rejections of a message this way
+            NoticeId::Unknown(id) => id.starts_with("msg_"),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for NoticeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for NoticeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NoticeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|id| NoticeId::from(id.as_str()))
     }
 }
 
@@ -211,19 +383,18 @@ impl From<JoinMessage> for IrcMessage {
 pub struct NoticeMessage {
     pub channel_login: Option<String>,
     pub message_text: String,
-    pub message_id: Option<String>,
+    pub message_id: Option<NoticeId>,
     pub deleted: bool,
     pub is_recent: bool,
     pub recent_timestamp: Option<u64>,
-    pub source: IrcMessage,
 }
 
-impl TryFrom<IrcMessage> for NoticeMessage {
+impl TryFrom<&IrcMessageRef<'_>> for NoticeMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<NoticeMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<NoticeMessage, ServerMessageParseError> {
         if source.command != "NOTICE" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(NoticeMessage {
@@ -233,7 +404,7 @@ impl TryFrom<IrcMessage> for NoticeMessage {
             message_text: source.try_get_param(1)?.to_owned(),
             message_id: source
                 .try_get_optional_nonempty_tag_value("msg-id")?
-                .map(|s| s.to_owned()),
+                .map(|id| NoticeId::from(id.as_ref())),
             deleted: source
                 .try_get_optional_bool("rm-deleted")?
                 .unwrap_or_default(),
@@ -241,14 +412,19 @@ impl TryFrom<IrcMessage> for NoticeMessage {
                 .try_get_optional_bool("historical")?
                 .unwrap_or_default(),
             recent_timestamp: source.try_get_timestamp("rm-received-ts").ok(),
-            source,
         })
     }
 }
 
-impl From<NoticeMessage> for IrcMessage {
-    fn from(msg: NoticeMessage) -> IrcMessage {
-        msg.source
+impl NoticeMessage {
+    /// Whether the server refused the token sent in PASS. These NOTICEs have
+    /// no `msg-id`, so they can only be told apart by their text.
+    pub fn is_login_failure(&self) -> bool {
+        self.message_id.is_none()
+            && matches!(
+                self.message_text.as_str(),
+                "Login authentication failed" | "Improperly formatted auth"
+            )
     }
 }
 
@@ -256,74 +432,50 @@ impl From<NoticeMessage> for IrcMessage {
 pub struct PartMessage {
     pub channel_login: String,
     pub user_login: String,
-    pub source: IrcMessage,
 }
 
-impl TryFrom<IrcMessage> for PartMessage {
+impl TryFrom<&IrcMessageRef<'_>> for PartMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<PartMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<PartMessage, ServerMessageParseError> {
         if source.command != "PART" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(PartMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
             user_login: source.try_get_prefix_nickname()?.to_owned(),
-            source,
         })
     }
 }
 
-impl From<PartMessage> for IrcMessage {
-    fn from(msg: PartMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct PingMessage {
-    pub source: IrcMessage,
-}
+pub struct PingMessage {}
 
-impl TryFrom<IrcMessage> for PingMessage {
+impl TryFrom<&IrcMessageRef<'_>> for PingMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<PingMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<PingMessage, ServerMessageParseError> {
         if source.command != "PING" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
-        Ok(PingMessage { source })
-    }
-}
-
-impl From<PingMessage> for IrcMessage {
-    fn from(msg: PingMessage) -> IrcMessage {
-        msg.source
+        Ok(PingMessage {})
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct PongMessage {
-    pub source: IrcMessage,
-}
+pub struct PongMessage {}
 
-impl TryFrom<IrcMessage> for PongMessage {
+impl TryFrom<&IrcMessageRef<'_>> for PongMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<PongMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<PongMessage, ServerMessageParseError> {
         if source.command != "PONG" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
-        Ok(PongMessage { source })
-    }
-}
-
-impl From<PongMessage> for IrcMessage {
-    fn from(msg: PongMessage) -> IrcMessage {
-        msg.source
+        Ok(PongMessage {})
     }
 }
 
@@ -343,44 +495,63 @@ pub struct PrivmsgMessage {
     pub badge_info: Vec<Badge>,
     pub badges: Vec<Badge>,
     pub bits: Option<u64>,
+    pub paid_chat: Option<PaidChat>,
+    /// Id of the channel points reward redeemed with the message.
+    pub custom_reward_id: Option<String>,
+    pub power_up: Option<PowerUp>,
     pub name_color: String,
     pub emotes: Vec<Emote>,
     pub message_id: String,
     pub deleted: bool,
     pub is_recent: bool,
     pub server_timestamp: u64,
-    pub source: IrcMessage,
+    pub shared_chat: Option<SharedChatSource>,
 }
 
-impl TryFrom<IrcMessage> for PrivmsgMessage {
+impl TryFrom<&IrcMessageRef<'_>> for PrivmsgMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<PrivmsgMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<PrivmsgMessage, ServerMessageParseError> {
         if source.command != "PRIVMSG" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         let (message_text, is_action) = source.try_get_message_text()?;
 
         let msg_id = source.try_get_tag_value("msg-id").ok();
 
+        let power_up = match msg_id.as_deref() {
+            Some("gigantified-emote-message") => Some(PowerUp::GigantifiedEmote),
+            Some("animated-message") => Some(PowerUp::Animated {
+                animation_id: source
+                    .try_get_optional_nonempty_tag_value("animation-id")?
+                    .map(Cow::into_owned),
+            }),
+            _ => None,
+        };
+
         Ok(PrivmsgMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
-            channel_id: source.try_get_nonempty_tag_value("room-id")?.to_owned(),
+            channel_id: source.try_get_nonempty_tag_value("room-id")?.into_owned(),
             sender: BasicUser {
-                id: source.try_get_nonempty_tag_value("user-id")?.to_owned(),
+                id: source.try_get_nonempty_tag_value("user-id")?.into_owned(),
                 login: source.try_get_prefix_nickname()?.to_owned(),
                 name: source
                     .try_get_nonempty_tag_value("display-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
             badge_info: source.try_get_badges("badge-info")?,
             badges: source.try_get_badges("badges")?,
             bits: source.try_get_optional_number("bits")?,
-            name_color: source.try_get_color("color")?.to_owned(),
+            paid_chat: source.try_get_optional_paid_chat()?,
+            custom_reward_id: source
+                .try_get_optional_nonempty_tag_value("custom-reward-id")?
+                .map(Cow::into_owned),
+            power_up,
+            name_color: source.try_get_color("color")?.into_owned(),
             emotes: source.try_get_emotes("emotes", message_text)?,
             server_timestamp: source.try_get_timestamp("tmi-sent-ts")?,
-            message_id: source.try_get_nonempty_tag_value("id")?.to_owned(),
+            message_id: source.try_get_nonempty_tag_value("id")?.into_owned(),
             message_text: message_text.to_owned(),
             reply: source.try_get_optional_reply()?,
             is_action,
@@ -397,40 +568,26 @@ impl TryFrom<IrcMessage> for PrivmsgMessage {
             is_recent: source
                 .try_get_optional_bool("historical")?
                 .unwrap_or_default(),
-            source,
+            shared_chat: source.try_get_optional_shared_chat_source()?,
         })
     }
 }
 
-impl From<PrivmsgMessage> for IrcMessage {
-    fn from(msg: PrivmsgMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct ReconnectMessage {
-    pub source: IrcMessage,
-}
+pub struct ReconnectMessage {}
 
-impl TryFrom<IrcMessage> for ReconnectMessage {
+impl TryFrom<&IrcMessageRef<'_>> for ReconnectMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<ReconnectMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<ReconnectMessage, ServerMessageParseError> {
         if source.command == "RECONNECT" {
-            Ok(ReconnectMessage { source })
+            Ok(ReconnectMessage {})
         } else {
-            Err(MismatchedCommand(source))
+            Err(MismatchedCommand(source.into()))
         }
     }
 }
 
-impl From<ReconnectMessage> for IrcMessage {
-    fn from(msg: ReconnectMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RoomStateMessage {
     pub channel_login: String,
@@ -440,7 +597,6 @@ pub struct RoomStateMessage {
     pub r9k: Option<bool>,
     pub slow_mode: Option<Duration>,
     pub subscribers_only: Option<bool>,
-    pub source: IrcMessage,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -449,17 +605,17 @@ pub enum FollowersOnlyMode {
     Enabled(Duration),
 }
 
-impl TryFrom<IrcMessage> for RoomStateMessage {
+impl TryFrom<&IrcMessageRef<'_>> for RoomStateMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<RoomStateMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<RoomStateMessage, ServerMessageParseError> {
         if source.command != "ROOMSTATE" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(RoomStateMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
-            channel_id: source.try_get_nonempty_tag_value("room-id")?.to_owned(),
+            channel_id: source.try_get_nonempty_tag_value("room-id")?.into_owned(),
             emote_only: source.try_get_optional_bool("emote-only")?,
             followers_only: source
                 .try_get_optional_number::<i64>("followers-only")?
@@ -472,17 +628,10 @@ impl TryFrom<IrcMessage> for RoomStateMessage {
                 .try_get_optional_number::<u64>("slow")?
                 .map(Duration::from_secs),
             subscribers_only: source.try_get_optional_bool("subs-only")?,
-            source,
         })
     }
 }
 
-impl From<RoomStateMessage> for IrcMessage {
-    fn from(msg: RoomStateMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserNoticeMessage {
     pub channel_login: String,
@@ -500,7 +649,7 @@ pub struct UserNoticeMessage {
     pub deleted: bool,
     pub is_recent: bool,
     pub server_timestamp: u64,
-    pub source: IrcMessage,
+    pub shared_chat: Option<SharedChatSource>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -511,13 +660,13 @@ pub struct SubGiftPromo {
 
 impl SubGiftPromo {
     fn parse_if_present(
-        source: &IrcMessage,
+        source: &IrcMessageRef<'_>,
     ) -> Result<Option<SubGiftPromo>, ServerMessageParseError> {
         if let (Some(total_gifts), Some(promo_name)) = (
             source.try_get_optional_number("msg-param-promo-gift-total")?,
             source
                 .try_get_optional_nonempty_tag_value("msg-param-promo-name")?
-                .map(|s| s.to_owned()),
+                .map(Cow::into_owned),
         ) {
             Ok(Some(SubGiftPromo {
                 total_gifts,
@@ -586,44 +735,73 @@ pub enum UserNoticeEvent {
     BitsBadgeTier {
         threshold: u64,
     },
+    /// A viewer reached a milestone such as a watch streak.
+    ViewerMilestone {
+        /// Kind of the milestone, e.g. `watch-streak`.
+        category: String,
+        value: u64,
+        /// Channel points the viewer was rewarded with.
+        reward: Option<u64>,
+    },
+    CharityDonation {
+        charity_name: String,
+        /// Donated amount in the currency's minor unit, the decimal places are
+        /// given by `exponent`.
+        amount: u64,
+        currency: String,
+        exponent: u32,
+    },
+    StandardPayForward {
+        /// `None` if the prior gifter was anonymous.
+        gifter: Option<BasicUser>,
+        recipient: BasicUser,
+    },
+    OneTapGift {
+        gift_id: String,
+        bits_spent: u64,
+    },
+    PrimeCommunityGiftReceived {
+        gift_name: String,
+        middle_man: Option<String>,
+        recipient: Option<String>,
+    },
+    RewardGift {
+        domain: String,
+        selected_count: u64,
+        total_reward_count: u64,
+        trigger_amount: u64,
+        trigger_type: String,
+    },
+    /// An event from another channel of the Shared Chat session.
+    SharedChatNotice {
+        event: Box<UserNoticeEvent>,
+    },
     Unknown,
 }
 
-impl TryFrom<IrcMessage> for UserNoticeMessage {
-    type Error = ServerMessageParseError;
-
-    fn try_from(source: IrcMessage) -> Result<UserNoticeMessage, ServerMessageParseError> {
-        if source.command != "USERNOTICE" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
-        }
-
-        let sender = BasicUser {
-            id: source.try_get_nonempty_tag_value("user-id")?.to_owned(),
-            login: source.try_get_nonempty_tag_value("login")?.to_owned(),
-            name: source
-                .try_get_nonempty_tag_value("display-name")?
-                .to_owned(),
-        };
-
-        let event_id = source.try_get_nonempty_tag_value("msg-id")?.to_owned();
-
-        let event = match event_id.as_str() {
+impl UserNoticeEvent {
+    fn parse(
+        source: &IrcMessageRef<'_>,
+        event_id: &str,
+        sender: &BasicUser,
+    ) -> Result<UserNoticeEvent, ServerMessageParseError> {
+        Ok(match event_id {
             "announcement" => UserNoticeEvent::Announcement {
                 color: source
                     .try_get_nonempty_tag_value("msg-param-color")?
-                    .to_owned(),
+                    .into_owned(),
             },
             "communitypayforward" => UserNoticeEvent::CommunityPayForward {
                 gifter: BasicUser {
                     id: source
                         .try_get_nonempty_tag_value("msg-param-prior-gifter-id")?
-                        .to_owned(),
+                        .into_owned(),
                     login: source
                         .try_get_nonempty_tag_value("msg-param-prior-gifter-user-name")?
-                        .to_owned(),
+                        .into_owned(),
                     name: source
                         .try_get_nonempty_tag_value("msg-param-prior-gifter-display-name")?
-                        .to_owned(),
+                        .into_owned(),
                 },
             },
             "sub" | "resub" => UserNoticeEvent::SubOrResub {
@@ -636,16 +814,16 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
                 },
                 sub_plan: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan")?
-                    .to_owned(),
+                    .into_owned(),
                 sub_plan_name: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
             "raid" => UserNoticeEvent::Raid {
                 viewer_count: source.try_get_number::<u64>("msg-param-viewerCount")?,
                 profile_image_url: source
                     .try_get_nonempty_tag_value("msg-param-profileImageURL")?
-                    .to_owned(),
+                    .into_owned(),
             },
             "unraid" => UserNoticeEvent::Unraid,
             "subgift" | "anonsubgift" => UserNoticeEvent::SubGift {
@@ -654,20 +832,20 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
                 recipient: BasicUser {
                     id: source
                         .try_get_nonempty_tag_value("msg-param-recipient-id")?
-                        .to_owned(),
+                        .into_owned(),
                     login: source
                         .try_get_nonempty_tag_value("msg-param-recipient-user-name")?
-                        .to_owned(),
+                        .into_owned(),
                     name: source
                         .try_get_nonempty_tag_value("msg-param-recipient-display-name")?
-                        .to_owned(),
+                        .into_owned(),
                 },
                 sub_plan: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan")?
-                    .to_owned(),
+                    .into_owned(),
                 sub_plan_name: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan-name")?
-                    .to_owned(),
+                    .into_owned(),
                 num_gifted_months: source.try_get_number("msg-param-gift-months")?,
                 sender_total_months: source
                     .try_get_optional_number("msg-param-sender-count")?
@@ -676,7 +854,7 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
             "primepaidupgrade" => UserNoticeEvent::PrimePaidUpgrade {
                 sub_plan: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan")?
-                    .to_owned(),
+                    .into_owned(),
             },
             _ if (sender.id == "274598607" && event_id == "submysterygift")
                 || event_id == "anonsubmysterygift" =>
@@ -685,7 +863,7 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
                     mass_gift_count: source.try_get_number("msg-param-mass-gift-count")?,
                     sub_plan: source
                         .try_get_nonempty_tag_value("msg-param-sub-plan")?
-                        .to_owned(),
+                        .into_owned(),
                 }
             }
             "submysterygift" => UserNoticeEvent::SubMysteryGift {
@@ -697,35 +875,143 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
                 },
                 sub_plan: source
                     .try_get_nonempty_tag_value("msg-param-sub-plan")?
-                    .to_owned(),
+                    .into_owned(),
             },
 
             "giftpaidupgrade" => UserNoticeEvent::GiftPaidUpgrade {
                 gifter_login: source
                     .try_get_nonempty_tag_value("msg-param-sender-login")?
-                    .to_owned(),
+                    .into_owned(),
                 gifter_name: source
                     .try_get_nonempty_tag_value("msg-param-sender-name")?
-                    .to_owned(),
-                promotion: SubGiftPromo::parse_if_present(&source)?,
+                    .into_owned(),
+                promotion: SubGiftPromo::parse_if_present(source)?,
             },
             "anongiftpaidupgrade" => UserNoticeEvent::AnonGiftPaidUpgrade {
-                promotion: SubGiftPromo::parse_if_present(&source)?,
+                promotion: SubGiftPromo::parse_if_present(source)?,
             },
             "ritual" => UserNoticeEvent::Ritual {
                 ritual_name: source
                     .try_get_nonempty_tag_value("msg-param-ritual-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
             "bitsbadgetier" => UserNoticeEvent::BitsBadgeTier {
                 threshold: source
                     .try_get_number::<u64>("msg-param-threshold")?
                     .to_owned(),
             },
+            "viewermilestone" => UserNoticeEvent::ViewerMilestone {
+                category: source
+                    .try_get_nonempty_tag_value("msg-param-category")?
+                    .into_owned(),
+                value: source.try_get_number("msg-param-value")?,
+                reward: source.try_get_optional_number("msg-param-copoReward")?,
+            },
+            "charitydonation" => UserNoticeEvent::CharityDonation {
+                charity_name: source
+                    .try_get_nonempty_tag_value("msg-param-charity-name")?
+                    .into_owned(),
+                amount: source.try_get_number("msg-param-donation-amount")?,
+                currency: source
+                    .try_get_nonempty_tag_value("msg-param-donation-currency")?
+                    .into_owned(),
+                exponent: source.try_get_number("msg-param-exponent")?,
+            },
+            "standardpayforward" => UserNoticeEvent::StandardPayForward {
+                // Unlike most boolean tags this one is `true` or `false`
+                gifter: if source
+                    .try_get_optional_nonempty_tag_value("msg-param-prior-gifter-anonymous")?
+                    .is_some_and(|anonymous| anonymous == "true")
+                {
+                    None
+                } else {
+                    Some(BasicUser {
+                        id: source
+                            .try_get_nonempty_tag_value("msg-param-prior-gifter-id")?
+                            .into_owned(),
+                        login: source
+                            .try_get_nonempty_tag_value("msg-param-prior-gifter-user-name")?
+                            .into_owned(),
+                        name: source
+                            .try_get_nonempty_tag_value("msg-param-prior-gifter-display-name")?
+                            .into_owned(),
+                    })
+                },
+                recipient: BasicUser {
+                    id: source
+                        .try_get_nonempty_tag_value("msg-param-recipient-id")?
+                        .into_owned(),
+                    login: source
+                        .try_get_nonempty_tag_value("msg-param-recipient-user-name")?
+                        .into_owned(),
+                    name: source
+                        .try_get_nonempty_tag_value("msg-param-recipient-display-name")?
+                        .into_owned(),
+                },
+            },
+            "onetapgift" => UserNoticeEvent::OneTapGift {
+                gift_id: source
+                    .try_get_nonempty_tag_value("msg-param-gift-id")?
+                    .into_owned(),
+                bits_spent: source.try_get_number("msg-param-bits-spent")?,
+            },
+            "primecommunitygiftreceived" => UserNoticeEvent::PrimeCommunityGiftReceived {
+                gift_name: source
+                    .try_get_nonempty_tag_value("msg-param-gift-name")?
+                    .into_owned(),
+                middle_man: source
+                    .try_get_optional_nonempty_tag_value("msg-param-middle-man")?
+                    .map(Cow::into_owned),
+                recipient: source
+                    .try_get_optional_nonempty_tag_value("msg-param-recipient")?
+                    .map(Cow::into_owned),
+            },
+            "rewardgift" => UserNoticeEvent::RewardGift {
+                domain: source
+                    .try_get_nonempty_tag_value("msg-param-domain")?
+                    .into_owned(),
+                selected_count: source.try_get_number("msg-param-selected-count")?,
+                total_reward_count: source.try_get_number("msg-param-total-reward-count")?,
+                trigger_amount: source.try_get_number("msg-param-trigger-amount")?,
+                trigger_type: source
+                    .try_get_nonempty_tag_value("msg-param-trigger-type")?
+                    .into_owned(),
+            },
             _ => UserNoticeEvent::Unknown,
+        })
+    }
+}
+
+impl TryFrom<&IrcMessageRef<'_>> for UserNoticeMessage {
+    type Error = ServerMessageParseError;
+
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<UserNoticeMessage, ServerMessageParseError> {
+        if source.command != "USERNOTICE" {
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
+        }
+
+        let sender = BasicUser {
+            id: source.try_get_nonempty_tag_value("user-id")?.into_owned(),
+            login: source.try_get_nonempty_tag_value("login")?.into_owned(),
+            name: source
+                .try_get_nonempty_tag_value("display-name")?
+                .into_owned(),
         };
 
-        let message_text = source.params.get(1).cloned();
+        let event_id = source.try_get_nonempty_tag_value("msg-id")?.into_owned();
+
+        let event = match event_id.as_str() {
+            "sharedchatnotice" => {
+                let source_event_id = source.try_get_nonempty_tag_value("source-msg-id")?;
+
+                UserNoticeEvent::SharedChatNotice {
+                    event: Box::new(UserNoticeEvent::parse(source, &source_event_id, &sender)?),
+                }
+            }
+            _ => UserNoticeEvent::parse(source, &event_id, &sender)?,
+        };
+
+        let message_text = source.params.get(1).map(|&text| text.to_owned());
 
         let emotes = if let Some(message_text) = &message_text {
             source.try_get_emotes("emotes", message_text)?
@@ -737,16 +1023,16 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
             .try_get_nonempty_tag_value("system-msg")
             .or_else(|e| {
                 if event_id == "announcement" {
-                    source.try_get_param(1)
+                    source.try_get_param(1).map(Cow::Borrowed)
                 } else {
                     Err(e)
                 }
             })?
-            .to_owned();
+            .into_owned();
 
         Ok(UserNoticeMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
-            channel_id: source.try_get_nonempty_tag_value("room-id")?.to_owned(),
+            channel_id: source.try_get_nonempty_tag_value("room-id")?.into_owned(),
             sender,
             message_text,
             system_message,
@@ -755,8 +1041,8 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
             badge_info: source.try_get_badges("badge-info")?,
             badges: source.try_get_badges("badges")?,
             emotes,
-            name_color: source.try_get_color("color")?.to_owned(),
-            message_id: source.try_get_nonempty_tag_value("id")?.to_owned(),
+            name_color: source.try_get_color("color")?.into_owned(),
+            message_id: source.try_get_nonempty_tag_value("id")?.into_owned(),
             deleted: source
                 .try_get_optional_bool("rm-deleted")?
                 .unwrap_or_default(),
@@ -764,17 +1050,11 @@ impl TryFrom<IrcMessage> for UserNoticeMessage {
                 .try_get_optional_bool("historical")?
                 .unwrap_or_default(),
             server_timestamp: source.try_get_timestamp("tmi-sent-ts")?.to_owned(),
-            source,
+            shared_chat: source.try_get_optional_shared_chat_source()?,
         })
     }
 }
 
-impl From<UserNoticeMessage> for IrcMessage {
-    fn from(msg: UserNoticeMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserStateMessage {
     pub channel_login: String,
@@ -783,37 +1063,40 @@ pub struct UserStateMessage {
     pub badges: Vec<Badge>,
     pub emote_sets: HashSet<String>,
     pub name_color: String,
-    pub source: IrcMessage,
+    /// Id of the message this USERSTATE acknowledges, if it was sent in
+    /// response to a PRIVMSG.
+    pub message_id: Option<String>,
+    /// The `client-nonce` tag of the acknowledged PRIVMSG.
+    pub client_nonce: Option<String>,
 }
 
-impl TryFrom<IrcMessage> for UserStateMessage {
+impl TryFrom<&IrcMessageRef<'_>> for UserStateMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<UserStateMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<UserStateMessage, ServerMessageParseError> {
         if source.command != "USERSTATE" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         Ok(UserStateMessage {
             channel_login: source.try_get_channel_login()?.to_owned(),
             user_name: source
                 .try_get_nonempty_tag_value("display-name")?
-                .to_owned(),
+                .into_owned(),
             badge_info: source.try_get_badges("badge-info")?,
             badges: source.try_get_badges("badges")?,
             emote_sets: source.try_get_emote_sets("emote-sets")?,
-            name_color: source.try_get_color("color")?.to_owned(),
-            source,
+            name_color: source.try_get_color("color")?.into_owned(),
+            message_id: source
+                .try_get_optional_nonempty_tag_value("id")?
+                .map(Cow::into_owned),
+            client_nonce: source
+                .try_get_optional_nonempty_tag_value("client-nonce")?
+                .map(Cow::into_owned),
         })
     }
 }
 
-impl From<UserStateMessage> for IrcMessage {
-    fn from(msg: UserStateMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WhisperMessage {
     pub recipient_login: String,
@@ -822,15 +1105,14 @@ pub struct WhisperMessage {
     pub name_color: String,
     pub badges: Vec<Badge>,
     pub emotes: Vec<Emote>,
-    pub source: IrcMessage,
 }
 
-impl TryFrom<IrcMessage> for WhisperMessage {
+impl TryFrom<&IrcMessageRef<'_>> for WhisperMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<WhisperMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<WhisperMessage, ServerMessageParseError> {
         if source.command != "WHISPER" {
-            return Err(ServerMessageParseError::MismatchedCommand(source));
+            return Err(ServerMessageParseError::MismatchedCommand(source.into()));
         }
 
         let message_text = source.try_get_param(1)?.to_owned();
@@ -839,27 +1121,20 @@ impl TryFrom<IrcMessage> for WhisperMessage {
         Ok(WhisperMessage {
             recipient_login: source.try_get_param(0)?.to_owned(),
             sender: BasicUser {
-                id: source.try_get_nonempty_tag_value("user-id")?.to_owned(),
+                id: source.try_get_nonempty_tag_value("user-id")?.into_owned(),
                 login: source.try_get_prefix_nickname()?.to_owned(),
                 name: source
                     .try_get_nonempty_tag_value("display-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
             message_text,
-            name_color: source.try_get_color("color")?.to_owned(),
+            name_color: source.try_get_color("color")?.into_owned(),
             badges: source.try_get_badges("badges")?,
             emotes,
-            source,
         })
     }
 }
 
-impl From<WhisperMessage> for IrcMessage {
-    fn from(msg: WhisperMessage) -> IrcMessage {
-        msg.source
-    }
-}
-
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum ServerMessageParseError {
     #[error("Could not parse IRC message {} as ServerMessage: That command's data is not parsed by this implementation", .0.as_raw_irc())]
@@ -902,21 +1177,22 @@ impl From<ServerMessageParseError> for IrcMessage {
     }
 }
 
-trait IrcMessageParseExt {
-    fn try_get_param(&self, index: usize) -> Result<&str, ServerMessageParseError>;
-    fn try_get_message_text(&self) -> Result<(&str, bool), ServerMessageParseError>;
-    fn try_get_tag_value(&self, key: &'static str) -> Result<&str, ServerMessageParseError>;
+trait IrcMessageParseExt<'a> {
+    fn try_get_param(&self, index: usize) -> Result<&'a str, ServerMessageParseError>;
+    fn try_get_message_text(&self) -> Result<(&'a str, bool), ServerMessageParseError>;
+    fn try_get_tag_value(&self, key: &'static str)
+    -> Result<Cow<'a, str>, ServerMessageParseError>;
     fn try_get_nonempty_tag_value(
         &self,
         key: &'static str,
-    ) -> Result<&str, ServerMessageParseError>;
+    ) -> Result<Cow<'a, str>, ServerMessageParseError>;
     fn try_get_optional_nonempty_tag_value(
         &self,
         key: &'static str,
-    ) -> Result<Option<&str>, ServerMessageParseError>;
-    fn try_get_channel_login(&self) -> Result<&str, ServerMessageParseError>;
-    fn try_get_optional_channel_login(&self) -> Result<Option<&str>, ServerMessageParseError>;
-    fn try_get_prefix_nickname(&self) -> Result<&str, ServerMessageParseError>;
+    ) -> Result<Option<Cow<'a, str>>, ServerMessageParseError>;
+    fn try_get_channel_login(&self) -> Result<&'a str, ServerMessageParseError>;
+    fn try_get_optional_channel_login(&self) -> Result<Option<&'a str>, ServerMessageParseError>;
+    fn try_get_prefix_nickname(&self) -> Result<&'a str, ServerMessageParseError>;
     fn try_get_emotes(
         &self,
         tag_key: &'static str,
@@ -927,7 +1203,8 @@ trait IrcMessageParseExt {
         tag_key: &'static str,
     ) -> Result<HashSet<String>, ServerMessageParseError>;
     fn try_get_badges(&self, tag_key: &'static str) -> Result<Vec<Badge>, ServerMessageParseError>;
-    fn try_get_color(&self, tag_key: &'static str) -> Result<&str, ServerMessageParseError>;
+    fn try_get_color(&self, tag_key: &'static str)
+    -> Result<Cow<'a, str>, ServerMessageParseError>;
     fn try_get_number<N: FromStr>(
         &self,
         tag_key: &'static str,
@@ -943,17 +1220,21 @@ trait IrcMessageParseExt {
     ) -> Result<Option<bool>, ServerMessageParseError>;
     fn try_get_timestamp(&self, tag_key: &'static str) -> Result<u64, ServerMessageParseError>;
     fn try_get_optional_reply(&self) -> Result<Option<Reply>, ServerMessageParseError>;
+    fn try_get_optional_shared_chat_source(
+        &self,
+    ) -> Result<Option<SharedChatSource>, ServerMessageParseError>;
+    fn try_get_optional_paid_chat(&self) -> Result<Option<PaidChat>, ServerMessageParseError>;
 }
 
-impl IrcMessageParseExt for IrcMessage {
-    fn try_get_param(&self, index: usize) -> Result<&str, ServerMessageParseError> {
+impl<'a> IrcMessageParseExt<'a> for IrcMessageRef<'a> {
+    fn try_get_param(&self, index: usize) -> Result<&'a str, ServerMessageParseError> {
         Ok(self
             .params
             .get(index)
-            .ok_or_else(|| MissingParameter(self.to_owned(), index))?)
+            .ok_or_else(|| MissingParameter(self.into(), index))?)
     }
 
-    fn try_get_message_text(&self) -> Result<(&str, bool), ServerMessageParseError> {
+    fn try_get_message_text(&self) -> Result<(&'a str, bool), ServerMessageParseError> {
         let mut message_text = self.try_get_param(1)?;
 
         let is_action =
@@ -966,50 +1247,49 @@ impl IrcMessageParseExt for IrcMessage {
         Ok((message_text, is_action))
     }
 
-    fn try_get_tag_value(&self, key: &'static str) -> Result<&str, ServerMessageParseError> {
-        match self.tags.0.get(key) {
+    fn try_get_tag_value(
+        &self,
+        key: &'static str,
+    ) -> Result<Cow<'a, str>, ServerMessageParseError> {
+        match self.tags.get(key) {
             Some(value) => Ok(value),
-            None => Err(MissingTag(self.to_owned(), key)),
+            None => Err(MissingTag(self.into(), key)),
         }
     }
 
     fn try_get_nonempty_tag_value(
         &self,
         key: &'static str,
-    ) -> Result<&str, ServerMessageParseError> {
-        match self.tags.0.get(key) {
-            Some(value) => match value.as_str() {
-                "" => Err(MissingTagValue(self.to_owned(), key)),
-                otherwise => Ok(otherwise),
-            },
-            None => Err(MissingTag(self.to_owned(), key)),
+    ) -> Result<Cow<'a, str>, ServerMessageParseError> {
+        match self.tags.get(key) {
+            Some(value) if value.is_empty() => Err(MissingTagValue(self.into(), key)),
+            Some(value) => Ok(value),
+            None => Err(MissingTag(self.into(), key)),
         }
     }
 
     fn try_get_optional_nonempty_tag_value(
         &self,
         key: &'static str,
-    ) -> Result<Option<&str>, ServerMessageParseError> {
-        match self.tags.0.get(key) {
-            Some(value) => match value.as_str() {
-                "" => Err(MissingTagValue(self.to_owned(), key)),
-                otherwise => Ok(Some(otherwise)),
-            },
+    ) -> Result<Option<Cow<'a, str>>, ServerMessageParseError> {
+        match self.tags.get(key) {
+            Some(value) if value.is_empty() => Err(MissingTagValue(self.into(), key)),
+            Some(value) => Ok(Some(value)),
             None => Ok(None),
         }
     }
 
-    fn try_get_channel_login(&self) -> Result<&str, ServerMessageParseError> {
+    fn try_get_channel_login(&self) -> Result<&'a str, ServerMessageParseError> {
         let param = self.try_get_param(0)?;
 
         if !param.starts_with('#') || param.len() < 2 {
-            return Err(MalformedChannel(self.to_owned()));
+            return Err(MalformedChannel(self.into()));
         }
 
         Ok(&param[1..])
     }
 
-    fn try_get_optional_channel_login(&self) -> Result<Option<&str>, ServerMessageParseError> {
+    fn try_get_optional_channel_login(&self) -> Result<Option<&'a str>, ServerMessageParseError> {
         let param = self.try_get_param(0)?;
 
         if param == "*" {
@@ -1017,21 +1297,18 @@ impl IrcMessageParseExt for IrcMessage {
         }
 
         if !param.starts_with('#') || param.len() < 2 {
-            return Err(MalformedChannel(self.to_owned()));
+            return Err(MalformedChannel(self.into()));
         }
 
         Ok(Some(&param[1..]))
     }
 
-    fn try_get_prefix_nickname(&self) -> Result<&str, ServerMessageParseError> {
-        match &self.prefix {
-            None => Err(MissingPrefix(self.to_owned())),
-            Some(IrcPrefix::HostOnly { host: _ }) => Err(MissingNickname(self.to_owned())),
-            Some(IrcPrefix::Full {
-                nick,
-                user: _,
-                host: _,
-            }) => Ok(nick),
+    fn try_get_prefix_nickname(&self) -> Result<&'a str, ServerMessageParseError> {
+        match self.prefix {
+            None => Err(MissingPrefix(self.into())),
+            // A prefix without `@` only consists of a host
+            Some(prefix) if !prefix.contains('@') => Err(MissingNickname(self.into())),
+            Some(prefix) => Ok(prefix.split(['!', '@']).next().unwrap_or(prefix)),
         }
     }
 
@@ -1048,7 +1325,7 @@ impl IrcMessageParseExt for IrcMessage {
 
         let mut emotes = Vec::new();
 
-        let make_error = || MalformedTagValue(self.to_owned(), tag_key, tag_value.to_owned());
+        let make_error = || MalformedTagValue(self.into(), tag_key, tag_value.to_string());
 
         for src in tag_value.split('/') {
             let (emote_id, indices_src) = src.split_once(':').ok_or_else(make_error)?;
@@ -1057,7 +1334,11 @@ impl IrcMessageParseExt for IrcMessage {
                 let (start, end) = range_src.split_once('-').ok_or_else(make_error)?;
 
                 let start = usize::from_str(start).map_err(|_| make_error())?;
-                let end = usize::from_str(end).map_err(|_| make_error())? + 1;
+                let end = usize::from_str(end)
+                    .ok()
+                    .and_then(|end| end.checked_add(1))
+                    .filter(|&end| end > start)
+                    .ok_or_else(make_error)?;
 
                 let code_length = end - start;
 
@@ -1103,7 +1384,7 @@ impl IrcMessageParseExt for IrcMessage {
 
         let mut badges = Vec::new();
 
-        let make_error = || MalformedTagValue(self.to_owned(), tag_key, tag_value.to_owned());
+        let make_error = || MalformedTagValue(self.into(), tag_key, tag_value.to_string());
 
         for src in tag_value.split(',') {
             let (name, version) = src.split_once('/').ok_or_else(make_error)?;
@@ -1117,9 +1398,11 @@ impl IrcMessageParseExt for IrcMessage {
         Ok(badges)
     }
 
-    fn try_get_color(&self, tag_key: &'static str) -> Result<&str, ServerMessageParseError> {
-        let tag_value = self.try_get_tag_value(tag_key)?;
-        Ok(tag_value)
+    fn try_get_color(
+        &self,
+        tag_key: &'static str,
+    ) -> Result<Cow<'a, str>, ServerMessageParseError> {
+        self.try_get_tag_value(tag_key)
     }
 
     fn try_get_number<N: FromStr>(
@@ -1127,8 +1410,8 @@ impl IrcMessageParseExt for IrcMessage {
         tag_key: &'static str,
     ) -> Result<N, ServerMessageParseError> {
         let tag_value = self.try_get_nonempty_tag_value(tag_key)?;
-        let number = N::from_str(tag_value)
-            .map_err(|_| MalformedTagValue(self.to_owned(), tag_key, tag_value.to_owned()))?;
+        let number = N::from_str(&tag_value)
+            .map_err(|_| MalformedTagValue(self.into(), tag_key, tag_value.to_string()))?;
         Ok(number)
     }
 
@@ -1140,16 +1423,12 @@ impl IrcMessageParseExt for IrcMessage {
         &self,
         tag_key: &'static str,
     ) -> Result<Option<N>, ServerMessageParseError> {
-        let tag_value = match self.tags.0.get(tag_key) {
-            Some(value) => match value.as_str() {
-                "" => return Err(MissingTagValue(self.to_owned(), tag_key)),
-                otherwise => otherwise,
-            },
-            None => return Ok(None),
+        let Some(tag_value) = self.try_get_optional_nonempty_tag_value(tag_key)? else {
+            return Ok(None);
         };
 
-        let number = N::from_str(tag_value)
-            .map_err(|_| MalformedTagValue(self.to_owned(), tag_key, tag_value.to_owned()))?;
+        let number = N::from_str(&tag_value)
+            .map_err(|_| MalformedTagValue(self.into(), tag_key, tag_value.to_string()))?;
         Ok(Some(number))
     }
 
@@ -1162,52 +1441,99 @@ impl IrcMessageParseExt for IrcMessage {
 
     fn try_get_timestamp(&self, tag_key: &'static str) -> Result<u64, ServerMessageParseError> {
         let tag_value = self.try_get_nonempty_tag_value(tag_key)?;
-        let milliseconds_since_epoch = u64::from_str(tag_value)
-            .map_err(|_| MalformedTagValue(self.to_owned(), tag_key, tag_value.to_owned()))?;
+        let milliseconds_since_epoch = u64::from_str(&tag_value)
+            .map_err(|_| MalformedTagValue(self.into(), tag_key, tag_value.to_string()))?;
 
         Ok(milliseconds_since_epoch)
     }
 
     fn try_get_optional_reply(&self) -> Result<Option<Reply>, ServerMessageParseError> {
-        if !self.tags.0.contains_key("reply-parent-msg-id") {
+        if !self.tags.contains_key("reply-parent-msg-id") {
             return Ok(None);
         }
 
         let parent = ReplyParent {
-            message_id: self.try_get_tag_value("reply-parent-msg-id")?.to_owned(),
+            message_id: self.try_get_tag_value("reply-parent-msg-id")?.into_owned(),
             user: BasicUser {
                 id: self
                     .try_get_nonempty_tag_value("reply-parent-user-id")?
-                    .to_owned(),
+                    .into_owned(),
                 login: self
                     .try_get_nonempty_tag_value("reply-parent-user-login")?
-                    .to_owned(),
+                    .into_owned(),
                 name: self
                     .try_get_nonempty_tag_value("reply-parent-display-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
-            message_text: self.try_get_tag_value("reply-parent-msg-body")?.to_owned(),
+            message_text: self
+                .try_get_tag_value("reply-parent-msg-body")?
+                .into_owned(),
         };
 
         let thread = ReplyThread {
             message_id: self
                 .try_get_tag_value("reply-thread-parent-msg-id")?
-                .to_owned(),
+                .into_owned(),
             user: BasicUser {
                 id: self
                     .try_get_nonempty_tag_value("reply-thread-parent-user-id")?
-                    .to_owned(),
+                    .into_owned(),
                 login: self
                     .try_get_nonempty_tag_value("reply-thread-parent-user-login")?
-                    .to_owned(),
+                    .into_owned(),
                 name: self
                     .try_get_nonempty_tag_value("reply-thread-parent-display-name")?
-                    .to_owned(),
+                    .into_owned(),
             },
         };
 
         Ok(Some(Reply { parent, thread }))
     }
+
+    fn try_get_optional_shared_chat_source(
+        &self,
+    ) -> Result<Option<SharedChatSource>, ServerMessageParseError> {
+        let Some(channel_id) = self.try_get_optional_nonempty_tag_value("source-room-id")? else {
+            return Ok(None);
+        };
+
+        let optional_badges = |tag_key| {
+            if self.tags.contains_key(tag_key) {
+                self.try_get_badges(tag_key)
+            } else {
+                Ok(vec![])
+            }
+        };
+
+        Ok(Some(SharedChatSource {
+            channel_id: channel_id.into_owned(),
+            message_id: self
+                .try_get_optional_nonempty_tag_value("source-id")?
+                .map(Cow::into_owned),
+            badges: optional_badges("source-badges")?,
+            badge_info: optional_badges("source-badge-info")?,
+        }))
+    }
+
+    fn try_get_optional_paid_chat(&self) -> Result<Option<PaidChat>, ServerMessageParseError> {
+        let Some(amount) = self.try_get_optional_number("pinned-chat-paid-amount")? else {
+            return Ok(None);
+        };
+
+        Ok(Some(PaidChat {
+            amount,
+            currency: self
+                .try_get_nonempty_tag_value("pinned-chat-paid-currency")?
+                .into_owned(),
+            exponent: self.try_get_number("pinned-chat-paid-exponent")?,
+            level: self
+                .try_get_nonempty_tag_value("pinned-chat-paid-level")?
+                .into_owned(),
+            is_system_message: self
+                .try_get_optional_bool("pinned-chat-paid-is-system-message")?
+                .unwrap_or_default(),
+        }))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -1217,6 +1543,7 @@ pub struct HiddenIrcMessage(pub(self) IrcMessage);
 #[serde(tag = "type", rename_all(serialize = "lowercase"))]
 #[non_exhaustive]
 pub enum ServerMessage {
+    Cap(CapMessage),
     ClearChat(ClearChatMessage),
     ClearMsg(ClearMsgMessage),
     GlobalUserState(GlobalUserStateMessage),
@@ -1234,13 +1561,14 @@ pub enum ServerMessage {
     Generic(HiddenIrcMessage),
 }
 
-impl TryFrom<IrcMessage> for ServerMessage {
+impl TryFrom<&IrcMessageRef<'_>> for ServerMessage {
     type Error = ServerMessageParseError;
 
-    fn try_from(source: IrcMessage) -> Result<ServerMessage, ServerMessageParseError> {
+    fn try_from(source: &IrcMessageRef<'_>) -> Result<ServerMessage, ServerMessageParseError> {
         use ServerMessage::*;
 
-        Ok(match source.command.as_str() {
+        Ok(match source.command.as_ref() {
+            "CAP" => Cap(CapMessage::try_from(source)?),
             "CLEARCHAT" => ClearChat(ClearChatMessage::try_from(source)?),
             "CLEARMSG" => ClearMsg(ClearMsgMessage::try_from(source)?),
             "GLOBALUSERSTATE" => GlobalUserState(GlobalUserStateMessage::try_from(source)?),
@@ -1255,61 +1583,44 @@ impl TryFrom<IrcMessage> for ServerMessage {
             "USERNOTICE" => UserNotice(UserNoticeMessage::try_from(source)?),
             "USERSTATE" => UserState(UserStateMessage::try_from(source)?),
             "WHISPER" => Whisper(WhisperMessage::try_from(source)?),
-            _ => Generic(HiddenIrcMessage(source)),
+            _ => Generic(HiddenIrcMessage(source.into())),
         })
     }
 }
 
-impl From<ServerMessage> for IrcMessage {
-    fn from(msg: ServerMessage) -> IrcMessage {
-        match msg {
-            ServerMessage::ClearChat(msg) => msg.source,
-            ServerMessage::ClearMsg(msg) => msg.source,
-            ServerMessage::GlobalUserState(msg) => msg.source,
-            ServerMessage::Join(msg) => msg.source,
-            ServerMessage::Notice(msg) => msg.source,
-            ServerMessage::Part(msg) => msg.source,
-            ServerMessage::Ping(msg) => msg.source,
-            ServerMessage::Pong(msg) => msg.source,
-            ServerMessage::Privmsg(msg) => msg.source,
-            ServerMessage::Reconnect(msg) => msg.source,
-            ServerMessage::RoomState(msg) => msg.source,
-            ServerMessage::UserNotice(msg) => msg.source,
-            ServerMessage::UserState(msg) => msg.source,
-            ServerMessage::Whisper(msg) => msg.source,
-            ServerMessage::Generic(msg) => msg.0,
-        }
+impl ServerMessage {
+    /// Parses a raw line into its typed message, only copying the parts of
+    /// the line the message keeps. Lines that are valid IRC but don't have
+    /// the data their command requires are returned as a generic message.
+    pub fn parse(source: &str) -> Result<ServerMessage, IrcParseError> {
+        let message = IrcMessageRef::parse(source)?;
+
+        Ok(ServerMessage::try_from(&message)
+            .unwrap_or_else(|error| ServerMessage::new_generic(IrcMessage::from(error))))
     }
-}
 
-impl ServerMessage {
-    pub fn source(&self) -> &IrcMessage {
+    /// Returns the unique id Twitch assigned to the message, if any.
+    pub fn message_id(&self) -> Option<&str> {
         match self {
-            ServerMessage::ClearChat(msg) => &msg.source,
-            ServerMessage::ClearMsg(msg) => &msg.source,
-            ServerMessage::GlobalUserState(msg) => &msg.source,
-            ServerMessage::Join(msg) => &msg.source,
-            ServerMessage::Notice(msg) => &msg.source,
-            ServerMessage::Part(msg) => &msg.source,
-            ServerMessage::Ping(msg) => &msg.source,
-            ServerMessage::Pong(msg) => &msg.source,
-            ServerMessage::Privmsg(msg) => &msg.source,
-            ServerMessage::Reconnect(msg) => &msg.source,
-            ServerMessage::RoomState(msg) => &msg.source,
-            ServerMessage::UserNotice(msg) => &msg.source,
-            ServerMessage::UserState(msg) => &msg.source,
-            ServerMessage::Whisper(msg) => &msg.source,
-            ServerMessage::Generic(msg) => &msg.0,
+            ServerMessage::Privmsg(msg) => Some(&msg.message_id),
+            ServerMessage::UserNotice(msg) => Some(&msg.message_id),
+            _ => None,
         }
     }
 
-    pub(crate) fn new_generic(message: IrcMessage) -> ServerMessage {
-        ServerMessage::Generic(HiddenIrcMessage(message))
+    /// Returns the id the message has in the channel it was sent in, if it was
+    /// delivered as part of a Shared Chat session.
+    pub fn shared_chat_source_id(&self) -> Option<&str> {
+        let shared_chat = match self {
+            ServerMessage::Privmsg(msg) => msg.shared_chat.as_ref(),
+            ServerMessage::UserNotice(msg) => msg.shared_chat.as_ref(),
+            _ => None,
+        };
+
+        shared_chat.and_then(|source| source.message_id.as_deref())
     }
-}
 
-impl AsRawIrc for ServerMessage {
-    fn format_as_raw_irc(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.source().format_as_raw_irc(f)
+    pub(crate) fn new_generic(message: IrcMessage) -> ServerMessage {
+        ServerMessage::Generic(HiddenIrcMessage(message))
     }
 }
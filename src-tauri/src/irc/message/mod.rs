@@ -1,15 +1,21 @@
+pub(crate) mod client;
 pub(crate) mod commands;
 pub(crate) mod prefix;
 pub(crate) mod tags;
 pub(crate) mod twitch;
 
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
 use std::fmt;
 use std::fmt::Write;
 
+pub use client::{ClientMessage, ClientMessageError};
 pub use commands::*;
 use prefix::IrcPrefix;
 use serde::{Deserialize, Serialize};
-pub use tags::IrcTags;
+pub use tags::{IrcTags, IrcTagsRef};
 use thiserror::Error;
 pub use twitch::*;
 
@@ -88,7 +94,27 @@ impl IrcMessage {
         }
     }
 
-    pub fn parse(mut source: &str) -> Result<IrcMessage, IrcParseError> {
+    pub fn parse(source: &str) -> Result<IrcMessage, IrcParseError> {
+        IrcMessageRef::parse(source).map(|message| IrcMessage::from(&message))
+    }
+}
+
+/// An IRC message borrowed from the raw line it was parsed from.
+///
+/// Unlike [`IrcMessage`], parsing this only allocates the list of parameters
+/// and tags, which makes it the cheaper choice for messages that are
+/// converted into a [`ServerMessage`] right away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrcMessageRef<'a> {
+    pub tags: IrcTagsRef<'a>,
+    /// Raw prefix without the leading `:`.
+    pub prefix: Option<&'a str>,
+    pub command: Cow<'a, str>,
+    pub params: Vec<&'a str>,
+}
+
+impl<'a> IrcMessageRef<'a> {
+    pub fn parse(mut source: &'a str) -> Result<IrcMessageRef<'a>, IrcParseError> {
         if source.chars().any(|c| c == '\r' || c == '\n') {
             return Err(IrcParseError::NewlinesInMessage);
         }
@@ -99,13 +125,9 @@ impl IrcMessage {
                 .ok_or(IrcParseError::NoSpaceAfterTags)?;
             source = remainder;
 
-            if tags_part.is_empty() {
-                return Err(IrcParseError::EmptyTagsDeclaration);
-            }
-
-            IrcTags::parse(tags_part)
+            IrcTagsRef::parse(tags_part)?
         } else {
-            IrcTags::new()
+            IrcTagsRef::default()
         };
 
         let prefix = if source.starts_with(':') {
@@ -118,14 +140,15 @@ impl IrcMessage {
                 return Err(IrcParseError::EmptyPrefixDeclaration);
             }
 
-            Some(IrcPrefix::parse(prefix_part))
+            Some(prefix_part)
         } else {
             None
         };
 
-        let mut command_split = source.splitn(2, ' ');
-        let mut command = command_split.next().unwrap().to_owned();
-        command.make_ascii_uppercase();
+        let (command, params_part) = match source.split_once(' ') {
+            Some((command, params_part)) => (command, Some(params_part)),
+            None => (source, None),
+        };
 
         if command.is_empty()
             || !command.chars().all(|c| c.is_ascii_alphabetic())
@@ -134,31 +157,40 @@ impl IrcMessage {
             return Err(IrcParseError::MalformedCommand);
         }
 
-        let mut params;
-        if let Some(params_part) = command_split.next() {
-            params = vec![];
-
-            let mut rest = Some(params_part);
-            while let Some(rest_str) = rest {
-                if let Some(sub_str) = rest_str.strip_prefix(':') {
-                    params.push(sub_str.to_owned());
-                    rest = None;
-                } else {
-                    let mut split = rest_str.splitn(2, ' ');
-                    let param = split.next().unwrap();
-                    rest = split.next();
-
-                    if param.is_empty() {
-                        return Err(IrcParseError::TooManySpacesInMiddleParams);
+        // Twitch always sends commands in uppercase, so this rarely allocates
+        let command = if command.bytes().any(|b| b.is_ascii_lowercase()) {
+            Cow::Owned(command.to_ascii_uppercase())
+        } else {
+            Cow::Borrowed(command)
+        };
+
+        let mut params = vec![];
+
+        let mut rest = params_part;
+        while let Some(rest_str) = rest {
+            if let Some(sub_str) = rest_str.strip_prefix(':') {
+                params.push(sub_str);
+                rest = None;
+            } else {
+                let param = match rest_str.split_once(' ') {
+                    Some((param, remainder)) => {
+                        rest = Some(remainder);
+                        param
+                    }
+                    None => {
+                        rest = None;
+                        rest_str
                     }
-                    params.push(param.to_owned());
+                };
+
+                if param.is_empty() {
+                    return Err(IrcParseError::TooManySpacesInMiddleParams);
                 }
+                params.push(param);
             }
-        } else {
-            params = vec![];
-        };
+        }
 
-        Ok(IrcMessage {
+        Ok(IrcMessageRef {
             tags,
             prefix,
             command,
@@ -167,6 +199,17 @@ impl IrcMessage {
     }
 }
 
+impl From<&IrcMessageRef<'_>> for IrcMessage {
+    fn from(message: &IrcMessageRef<'_>) -> Self {
+        IrcMessage {
+            tags: IrcTags::from(&message.tags),
+            prefix: message.prefix.map(IrcPrefix::parse),
+            command: message.command.clone().into_owned(),
+            params: message.params.iter().map(|&p| p.to_owned()).collect(),
+        }
+    }
+}
+
 impl AsRawIrc for IrcMessage {
     fn format_as_raw_irc(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if !self.tags.0.is_empty() {
@@ -187,8 +230,9 @@ impl AsRawIrc for IrcMessage {
             if !param.contains(' ') && !param.is_empty() && !param.starts_with(':') {
                 write!(f, " {param}")?;
             } else {
+                // Only the last parameter can be written as a trailing one,
+                // anything after it can't be represented and is left out
                 write!(f, " :{param}")?;
-                // TODO should there be a panic if this is not the last parameter?
                 break;
             }
         }
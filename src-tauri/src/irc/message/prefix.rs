@@ -18,24 +18,21 @@ pub enum IrcPrefix {
 
 impl IrcPrefix {
     pub fn parse(source: &str) -> IrcPrefix {
-        if !source.contains('@') {
-            IrcPrefix::HostOnly {
+        let Some((nick_and_user, host)) = source.split_once('@') else {
+            return IrcPrefix::HostOnly {
                 host: source.to_owned(),
-            }
-        } else {
-            let mut at_split = source.splitn(2, '@');
-            let nick_and_user = at_split.next().unwrap();
-            let host = at_split.next();
-
-            let mut exc_split = nick_and_user.splitn(2, '!');
-            let nick = exc_split.next();
-            let user = exc_split.next();
-
-            IrcPrefix::Full {
-                nick: nick.unwrap().to_owned(),
-                user: user.map(|s| s.to_owned()),
-                host: host.map(|s| s.to_owned()),
-            }
+            };
+        };
+
+        let (nick, user) = match nick_and_user.split_once('!') {
+            Some((nick, user)) => (nick, Some(user)),
+            None => (nick_and_user, None),
+        };
+
+        IrcPrefix::Full {
+            nick: nick.to_owned(),
+            user: user.map(|s| s.to_owned()),
+            host: Some(host.to_owned()),
         }
     }
 }
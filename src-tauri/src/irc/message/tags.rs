@@ -1,13 +1,19 @@
-use std::collections::hash_map::RandomState;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
 use std::fmt;
 use std::fmt::Write;
 
 use serde::{Deserialize, Serialize};
 
-use super::AsRawIrc;
+use super::{AsRawIrc, IrcParseError};
+
+pub(super) fn decode_tag_value(raw: &str) -> Cow<'_, str> {
+    // Most values contain no escape sequences and can be borrowed as is
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
 
-fn decode_tag_value(raw: &str) -> String {
     let mut output = String::with_capacity(raw.len());
 
     let mut iter = raw.chars();
@@ -28,10 +34,10 @@ fn decode_tag_value(raw: &str) -> String {
             output.push(c);
         }
     }
-    output
+    Cow::Owned(output)
 }
 
-fn encode_tag_value(raw: &str) -> String {
+pub(super) fn encode_tag_value(raw: &str) -> String {
     let mut output = String::with_capacity((raw.len() as f64 * 1.2) as usize);
 
     for c in raw.chars() {
@@ -57,28 +63,19 @@ impl IrcTags {
         IrcTags(HashMap::new())
     }
 
-    /// Panics if `source` is an empty string.
-    pub fn parse(source: &str) -> IrcTags {
-        if source.is_empty() {
-            panic!("invalid input")
-        }
-
-        let mut tags = IrcTags::new();
-
-        for raw_tag in source.split(';') {
-            let mut tag_split = raw_tag.splitn(2, '=');
-
-            // always expected to be present, even splitting an empty string yields [""]
-            let key = tag_split.next().unwrap();
-            // can be missing if no = is present
-            let value = tag_split
-                .next()
-                .map_or_else(|| "".to_owned(), decode_tag_value);
-
-            tags.0.insert(key.to_owned(), value);
-        }
+    /// Fails if `source` is an empty string.
+    pub fn parse(source: &str) -> Result<IrcTags, IrcParseError> {
+        IrcTagsRef::parse(source).map(|tags| IrcTags::from(&tags))
+    }
+}
 
-        tags
+impl From<&IrcTagsRef<'_>> for IrcTags {
+    fn from(tags: &IrcTagsRef<'_>) -> Self {
+        IrcTags(
+            tags.iter()
+                .map(|(key, value)| (key.to_owned(), value.into_owned()))
+                .collect(),
+        )
     }
 }
 
@@ -111,6 +108,58 @@ impl AsRawIrc for IrcTags {
     }
 }
 
+/// Tags borrowed from a raw IRC message. Values are only decoded when they are
+/// looked up.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct IrcTagsRef<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> IrcTagsRef<'a> {
+    /// Fails if `source` is an empty string.
+    pub fn parse(source: &'a str) -> Result<IrcTagsRef<'a>, IrcParseError> {
+        if source.is_empty() {
+            return Err(IrcParseError::EmptyTagsDeclaration);
+        }
+
+        Ok(IrcTagsRef(
+            source
+                .split(';')
+                .map(|raw_tag| {
+                    // value can be missing if no = is present
+                    raw_tag.split_once('=').unwrap_or((raw_tag, ""))
+                })
+                .collect(),
+        ))
+    }
+
+    fn raw(&self, key: &str) -> Option<&'a str> {
+        // Later occurrences of a key take precedence like they would when
+        // inserted into a map
+        self.0
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Cow<'a, str>> {
+        self.raw(key).map(decode_tag_value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.raw(key).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, Cow<'a, str>)> + '_ {
+        self.0
+            .iter()
+            .map(|(key, value)| (*key, decode_tag_value(value)))
+    }
+}
+
 impl PartialEq<HashMap<String, String>> for IrcTags {
     fn eq(&self, other: &HashMap<String, String, RandomState>) -> bool {
         &self.0 == other
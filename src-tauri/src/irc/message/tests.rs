@@ -0,0 +1,566 @@
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+
+use super::prefix::IrcPrefix;
+use super::tags::{decode_tag_value, encode_tag_value};
+use super::{
+    AsRawIrc, BasicUser, CapMessage, ClientMessage, ClientMessageError, IrcMessage, IrcMessageRef,
+    IrcParseError, IrcTags, NoticeId, PaidChat, PowerUp, PrivmsgMessage, ServerMessage,
+    ServerMessageParseError, UserNoticeEvent, UserNoticeMessage, WhisperMessage,
+};
+use crate::irc;
+
+const CORPUS: &str = include_str!("fixtures/corpus.txt");
+
+fn parse_privmsg(fixture: &str) -> PrivmsgMessage {
+    let message = IrcMessageRef::parse(fixture.trim_end()).expect("fixture is valid IRC");
+    PrivmsgMessage::try_from(&message).expect("fixture is a valid PRIVMSG")
+}
+
+fn parse_user_notice(fixture: &str) -> UserNoticeMessage {
+    let message = IrcMessageRef::parse(fixture.trim_end()).expect("fixture is valid IRC");
+    UserNoticeMessage::try_from(&message).expect("fixture is a valid USERNOTICE")
+}
+
+fn user(id: &str, login: &str, name: &str) -> BasicUser {
+    BasicUser {
+        id: id.into(),
+        login: login.into(),
+        name: name.into(),
+    }
+}
+
+#[test]
+fn viewer_milestone() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/viewermilestone.txt"));
+
+    assert_eq!(msg.event_id, "viewermilestone");
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::ViewerMilestone {
+            category: "watch-streak".into(),
+            value: 7,
+            reward: Some(450),
+        }
+    );
+    assert_eq!(msg.message_text.as_deref(), Some("Love the streams"));
+}
+
+#[test]
+fn charity_donation() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/charitydonation.txt"));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::CharityDonation {
+            charity_name: "Direct Relief".into(),
+            amount: 2500,
+            currency: "USD".into(),
+            exponent: 2,
+        }
+    );
+}
+
+#[test]
+fn standard_pay_forward() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/standardpayforward.txt"));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::StandardPayForward {
+            gifter: Some(user("34567890", "gifter", "Gifter")),
+            recipient: user("45678901", "lucky", "Lucky"),
+        }
+    );
+}
+
+#[test]
+fn standard_pay_forward_from_anonymous_gifter() {
+    let msg = parse_user_notice(include_str!(
+        "fixtures/usernotice/standardpayforward_anonymous.txt"
+    ));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::StandardPayForward {
+            gifter: None,
+            recipient: user("45678901", "lucky", "Lucky"),
+        }
+    );
+}
+
+#[test]
+fn one_tap_gift() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/onetapgift.txt"));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::OneTapGift {
+            gift_id: "heart".into(),
+            bits_spent: 100,
+        }
+    );
+}
+
+#[test]
+fn prime_community_gift_received() {
+    let msg = parse_user_notice(include_str!(
+        "fixtures/usernotice/primecommunitygiftreceived.txt"
+    ));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::PrimeCommunityGiftReceived {
+            gift_name: "Prime Gaming Loot".into(),
+            middle_man: Some("forsen".into()),
+            recipient: Some("receiver".into()),
+        }
+    );
+}
+
+#[test]
+fn reward_gift() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/rewardgift.txt"));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::RewardGift {
+            domain: "seasonal-food-drive".into(),
+            selected_count: 5,
+            total_reward_count: 5,
+            trigger_amount: 1,
+            trigger_type: "SUBGIFT".into(),
+        }
+    );
+}
+
+#[test]
+fn shared_chat_notice() {
+    let msg = parse_user_notice(include_str!("fixtures/usernotice/sharedchatnotice.txt"));
+
+    assert_eq!(
+        msg.event,
+        UserNoticeEvent::SharedChatNotice {
+            event: Box::new(UserNoticeEvent::SubOrResub {
+                is_resub: true,
+                cumulative_months: 3,
+                streak_months: None,
+                sub_plan: "1000".into(),
+                sub_plan_name: "Channel Subscription".into(),
+            }),
+        }
+    );
+
+    let shared_chat = msg.shared_chat.expect("notice has a Shared Chat source");
+    assert_eq!(shared_chat.channel_id, "11148817");
+    assert_eq!(
+        shared_chat.message_id.as_deref(),
+        Some("9f8e7d6c-0000-4000-8000-000000000009")
+    );
+}
+
+#[test]
+fn paid_chat() {
+    let msg = parse_privmsg(include_str!("fixtures/privmsg/paidchat.txt"));
+
+    assert_eq!(
+        msg.paid_chat,
+        Some(PaidChat {
+            amount: 500,
+            currency: "USD".into(),
+            exponent: 2,
+            level: "ONE".into(),
+            is_system_message: false,
+        })
+    );
+    assert_eq!(msg.custom_reward_id, None);
+    assert_eq!(msg.power_up, None);
+}
+
+#[test]
+fn custom_reward_redemption() {
+    let msg = parse_privmsg(include_str!("fixtures/privmsg/customreward.txt"));
+
+    assert_eq!(
+        msg.custom_reward_id.as_deref(),
+        Some("a1b2c3d4-0000-4000-8000-00000000abcd")
+    );
+    assert_eq!(msg.paid_chat, None);
+}
+
+#[test]
+fn animated_message() {
+    let msg = parse_privmsg(include_str!("fixtures/privmsg/animatedmessage.txt"));
+
+    assert_eq!(
+        msg.power_up,
+        Some(PowerUp::Animated {
+            animation_id: Some("rainbow-eclipse".into()),
+        })
+    );
+    assert!(!msg.is_highlighted);
+}
+
+#[test]
+fn gigantified_emote() {
+    let msg = parse_privmsg(include_str!("fixtures/privmsg/gigantifiedemote.txt"));
+
+    assert_eq!(msg.power_up, Some(PowerUp::GigantifiedEmote));
+}
+
+fn variant_name(message: &ServerMessage) -> &'static str {
+    // Deliberately exhaustive so that new variants have to be added to the
+    // corpus as well
+    match message {
+        ServerMessage::Cap(_) => "cap",
+        ServerMessage::ClearChat(_) => "clearchat",
+        ServerMessage::ClearMsg(_) => "clearmsg",
+        ServerMessage::GlobalUserState(_) => "globaluserstate",
+        ServerMessage::Join(_) => "join",
+        ServerMessage::Notice(_) => "notice",
+        ServerMessage::Part(_) => "part",
+        ServerMessage::Ping(_) => "ping",
+        ServerMessage::Pong(_) => "pong",
+        ServerMessage::Privmsg(_) => "privmsg",
+        ServerMessage::Reconnect(_) => "reconnect",
+        ServerMessage::RoomState(_) => "roomstate",
+        ServerMessage::UserNotice(_) => "usernotice",
+        ServerMessage::UserState(_) => "userstate",
+        ServerMessage::Whisper(_) => "whisper",
+        ServerMessage::Generic(_) => "generic",
+    }
+}
+
+#[test]
+fn corpus_covers_every_server_message() {
+    let mut seen = HashSet::new();
+
+    for line in CORPUS.lines() {
+        let message = IrcMessageRef::parse(line).expect("corpus line is valid IRC");
+
+        let parsed = ServerMessage::try_from(&message)
+            .unwrap_or_else(|error| panic!("failed to parse {line:?}: {error}"));
+        seen.insert(variant_name(&parsed));
+    }
+
+    let expected = HashSet::from([
+        "cap",
+        "clearchat",
+        "clearmsg",
+        "globaluserstate",
+        "join",
+        "notice",
+        "part",
+        "ping",
+        "pong",
+        "privmsg",
+        "reconnect",
+        "roomstate",
+        "usernotice",
+        "userstate",
+        "whisper",
+        "generic",
+    ]);
+
+    assert_eq!(seen, expected);
+}
+
+#[test]
+fn corpus_round_trips() {
+    for line in CORPUS.lines() {
+        let message = IrcMessage::parse(line).expect("corpus line is valid IRC");
+
+        assert_eq!(IrcMessage::parse(&message.as_raw_irc()), Ok(message));
+    }
+}
+
+#[test]
+fn trailing_param_is_written_last() {
+    let message = irc!["PRIVMSG", "#forsen", ":)"];
+    assert_eq!(message.as_raw_irc(), "PRIVMSG #forsen ::)");
+
+    let message = irc!["PRIVMSG", "#forsen", ""];
+    assert_eq!(message.as_raw_irc(), "PRIVMSG #forsen :");
+}
+
+#[test]
+fn params_after_the_trailing_one_are_dropped() {
+    let message = irc!["PRIVMSG", "#forsen", "hello world", "lost"];
+    assert_eq!(message.as_raw_irc(), "PRIVMSG #forsen :hello world");
+}
+
+fn tag_key() -> impl Strategy<Value = String> {
+    "([a-z]+(\\.[a-z]+)*/)?[a-z][a-z0-9-]{0,20}"
+}
+
+fn prefix() -> impl Strategy<Value = IrcPrefix> {
+    let name = "[a-z0-9_]{1,25}";
+    let host = "[a-z0-9.-]{1,40}";
+
+    prop_oneof![
+        host.prop_map(|host| IrcPrefix::HostOnly { host }),
+        (name, proptest::option::of(name), host).prop_map(|(nick, user, host)| {
+            IrcPrefix::Full {
+                nick,
+                user,
+                host: Some(host),
+            }
+        }),
+    ]
+}
+
+fn command() -> impl Strategy<Value = String> {
+    prop_oneof!["[A-Z]{1,16}", "[0-9]{3}"]
+}
+
+/// Parameters with anything but newlines, mostly ones that can be written as
+/// middle parameters.
+fn params() -> impl Strategy<Value = Vec<String>> {
+    proptest::collection::vec(
+        prop_oneof![3 => "[^\\s:][^\\s]{0,20}", 1 => "[^\\r\\n]{0,60}"],
+        0..7,
+    )
+}
+
+/// Returns the parameters that are written out, which ends with the first one
+/// that has to be written as the trailing parameter.
+fn written_params(params: &[String]) -> Vec<String> {
+    let trailing = params
+        .iter()
+        .position(|param| param.is_empty() || param.contains(' ') || param.starts_with(':'));
+
+    match trailing {
+        Some(pos) => params[..=pos].to_vec(),
+        None => params.to_vec(),
+    }
+}
+
+fn message() -> impl Strategy<Value = IrcMessage> {
+    (
+        proptest::collection::hash_map(tag_key(), any::<String>(), 0..8),
+        proptest::option::of(prefix()),
+        command(),
+        params(),
+    )
+        .prop_map(|(tags, prefix, command, params)| IrcMessage {
+            tags: IrcTags::from(tags),
+            prefix,
+            command,
+            params,
+        })
+}
+
+proptest! {
+    #[test]
+    fn tag_value_round_trips(value in any::<String>()) {
+        let encoded = encode_tag_value(&value);
+        prop_assert_eq!(decode_tag_value(&encoded), value.as_str());
+    }
+
+    #[test]
+    fn message_round_trips(message in message()) {
+        let expected = IrcMessage {
+            params: written_params(&message.params),
+            ..message.clone()
+        };
+
+        prop_assert_eq!(IrcMessage::parse(&message.as_raw_irc()), Ok(expected));
+    }
+
+    #[test]
+    fn tags_round_trip(tags in proptest::collection::hash_map(tag_key(), any::<String>(), 1..8)) {
+        let tags = IrcTags::from(tags);
+        let raw = format!("@{} PING", tags.as_raw_irc());
+
+        prop_assert_eq!(IrcMessage::parse(&raw).map(|message| message.tags), Ok(tags));
+    }
+
+    #[test]
+    fn parse_never_panics(line in any::<String>()) {
+        let _ = ServerMessage::parse(&line);
+    }
+}
+
+#[test]
+fn empty_tags_are_rejected() {
+    assert_eq!(IrcTags::parse(""), Err(IrcParseError::EmptyTagsDeclaration));
+    assert_eq!(
+        IrcMessage::parse("@ PING"),
+        Err(IrcParseError::EmptyTagsDeclaration)
+    );
+}
+
+#[test]
+fn malformed_emote_ranges_are_rejected() {
+    for emotes in ["25:5-2", "25:0-18446744073709551615", "25:x-1", "25"] {
+        let line = format!(
+            "@badges=;color=;display-name=randers;emotes={emotes};user-id=40286300 :randers!randers@randers.tmi.twitch.tv WHISPER randers811 :Kappa"
+        );
+        let message = IrcMessageRef::parse(&line).expect("line is valid IRC");
+
+        assert!(matches!(
+            WhisperMessage::try_from(&message),
+            Err(ServerMessageParseError::MalformedTagValue(_, "emotes", _))
+        ));
+    }
+}
+
+#[test]
+fn client_messages_are_written_as_raw_irc() {
+    let privmsg = ClientMessage::Privmsg {
+        channel_login: "forsen".into(),
+        message: "hi chat".into(),
+        reply_parent_id: Some("abc".into()),
+        client_nonce: None,
+    };
+    assert_eq!(
+        IrcMessage::from(privmsg).as_raw_irc(),
+        "@reply-parent-msg-id=abc PRIVMSG #forsen :hi chat"
+    );
+
+    let action = ClientMessage::Action {
+        channel_login: "forsen".into(),
+        message: "waves".into(),
+    };
+    assert_eq!(
+        IrcMessage::from(action).as_raw_irc(),
+        "PRIVMSG #forsen :\u{0001}ACTION waves\u{0001}"
+    );
+
+    for token in ["oauth:1234", "1234"] {
+        let pass = ClientMessage::Pass {
+            token: token.into(),
+        };
+        assert_eq!(IrcMessage::from(pass).as_raw_irc(), "PASS oauth:1234");
+    }
+}
+
+#[test]
+fn invalid_client_messages_are_rejected() {
+    let privmsg = ClientMessage::Privmsg {
+        channel_login: "forsen".into(),
+        message: "hi\r\nPRIVMSG #pajlada :hi".into(),
+        reply_parent_id: None,
+        client_nonce: None,
+    };
+    assert_eq!(
+        privmsg.validate(),
+        Err(ClientMessageError::NewlinesInMessage)
+    );
+
+    let join = ClientMessage::Join {
+        channel_login: "forsen pajlada".into(),
+    };
+    assert_eq!(
+        join.validate(),
+        Err(ClientMessageError::MalformedParameter("channel login"))
+    );
+}
+
+#[test]
+fn cap_answers() {
+    let ack = ServerMessage::parse(
+        ":tmi.twitch.tv CAP * ACK :twitch.tv/tags twitch.tv/commands twitch.tv/membership",
+    );
+    assert!(matches!(
+        ack,
+        Ok(ServerMessage::Cap(CapMessage { acknowledged: true, capabilities }))
+            if capabilities == ["twitch.tv/tags", "twitch.tv/commands", "twitch.tv/membership"]
+    ));
+
+    let nak = ServerMessage::parse(":tmi.twitch.tv CAP * NAK :twitch.tv/foo");
+    assert!(matches!(
+        nak,
+        Ok(ServerMessage::Cap(CapMessage { acknowledged: false, capabilities }))
+            if capabilities == ["twitch.tv/foo"]
+    ));
+
+    let ls = ServerMessage::parse(":tmi.twitch.tv CAP * LS :twitch.tv/tags");
+    assert!(matches!(ls, Ok(ServerMessage::Generic(_))));
+}
+
+#[test]
+fn login_failures() {
+    for line in [
+        ":tmi.twitch.tv NOTICE * :Login authentication failed",
+        ":tmi.twitch.tv NOTICE * :Improperly formatted auth",
+    ] {
+        let Ok(ServerMessage::Notice(notice)) = ServerMessage::parse(line) else {
+            panic!("{line:?} is not a NOTICE");
+        };
+
+        assert!(notice.is_login_failure());
+    }
+
+    let Ok(ServerMessage::Notice(notice)) = ServerMessage::parse(
+        "@msg-id=msg_banned :tmi.twitch.tv NOTICE #forsen :You are permanently banned from talking in forsen.",
+    ) else {
+        panic!("line is not a NOTICE");
+    };
+
+    assert!(!notice.is_login_failure());
+}
+
+#[test]
+fn notice_ids() {
+    for (id, notice_id) in [
+        ("msg_duplicate", NoticeId::MsgDuplicate),
+        ("slow_on", NoticeId::SlowOn),
+        ("tos_ban", NoticeId::TosBan),
+        ("msg_new_rule", NoticeId::Unknown("msg_new_rule".into())),
+        ("host_on", NoticeId::Unknown("host_on".into())),
+    ] {
+        assert_eq!(NoticeId::from(id), notice_id);
+        assert_eq!(notice_id.to_string(), id);
+        assert_eq!(
+            serde_json::to_value(&notice_id).unwrap(),
+            serde_json::json!(id)
+        );
+    }
+
+    let Ok(ServerMessage::Notice(notice)) = ServerMessage::parse(
+        "@msg-id=msg_ratelimit :tmi.twitch.tv NOTICE #forsen :Your message was not sent because you are sending messages too quickly.",
+    ) else {
+        panic!("line is not a NOTICE");
+    };
+
+    assert_eq!(notice.message_id, Some(NoticeId::MsgRateLimit));
+}
+
+#[test]
+fn notice_id_classification() {
+    for id in [
+        "msg_duplicate",
+        "msg_slowmode",
+        "msg_ratelimit",
+        "msg_banned",
+        "msg_timedout",
+        "msg_emoteonly",
+        "msg_followersonly",
+        "msg_subsonly",
+        "msg_r9k",
+        "msg_channel_suspended",
+        "msg_verified_email",
+        "msg_new_rule",
+    ] {
+        assert!(NoticeId::from(id).is_send_failure(), "{id}");
+    }
+
+    for id in [
+        "slow_on",
+        "emote_only_on",
+        "host_on",
+        "ban_success",
+        "no_permission",
+    ] {
+        let notice_id = NoticeId::from(id);
+
+        assert!(!notice_id.is_send_failure(), "{id}");
+        assert!(!notice_id.is_join_failure(), "{id}");
+    }
+
+    for id in ["msg_banned", "msg_channel_suspended", "tos_ban"] {
+        assert!(NoticeId::from(id).is_join_failure(), "{id}");
+    }
+}
@@ -40,3 +40,43 @@ pub struct Reply {
     pub parent: ReplyParent,
     pub thread: ReplyThread,
 }
+
+/// Origin of a message delivered to every channel of a Shared Chat session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedChatSource {
+    /// Id of the channel the message was sent in.
+    pub channel_id: String,
+    /// Id of the message in the channel it was sent in. The same message has a
+    /// different id in every other channel of the session.
+    pub message_id: Option<String>,
+    /// Badges of the sender in the channel the message was sent in.
+    pub badges: Vec<Badge>,
+    pub badge_info: Vec<Badge>,
+}
+
+/// Payment attached to a message sent with Paid Chat (formerly Hype Chat).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaidChat {
+    /// Paid amount in the currency's minor unit, the decimal places are given
+    /// by `exponent`.
+    pub amount: u64,
+    /// ISO 4217 code of the currency.
+    pub currency: String,
+    pub exponent: u32,
+    /// Tier of the message from `ONE` to `TEN`, which determines how long it
+    /// stays pinned.
+    pub level: String,
+    /// Whether the message text was generated by Twitch because the sender
+    /// paid without writing a message.
+    pub is_system_message: bool,
+}
+
+/// Bits-powered effect applied to a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all(serialize = "snake_case"))]
+pub enum PowerUp {
+    /// The last emote in the message is shown enlarged.
+    GigantifiedEmote,
+    /// The message is shown with an animated style.
+    Animated { animation_id: Option<String> },
+}
@@ -7,19 +7,20 @@ mod config;
 mod connection;
 mod error;
 pub mod message;
-pub mod websocket;
+pub mod transport;
 
-pub use client::IrcClient;
+pub use client::{ConnectionStatus, IrcClient, PoolMetrics};
 use config::ClientConfig;
-use error::Error;
+pub use error::SendError;
+use error::{Error, JoinError};
 use message::ServerMessage;
 use tauri::ipc::Channel;
 use tauri::{State, async_runtime};
 use tokio::sync::Mutex;
 use tracing::Instrument;
+use transport::TransportKind;
 
 use crate::AppState;
-use crate::api::get_access_token;
 use crate::error::Error as AppError;
 
 #[tracing::instrument(skip_all)]
@@ -27,24 +28,35 @@ use crate::error::Error as AppError;
 pub async fn connect_irc(
     state: State<'_, Mutex<AppState>>,
     channel: Channel<ServerMessage>,
+    status_channel: Channel<ConnectionStatus>,
+    transport: Option<TransportKind>,
+    collapse_shared_chat: Option<bool>,
 ) -> Result<(), AppError> {
     let mut guard = state.lock().await;
-    let token = get_access_token(&guard)?;
-    let login = token.login.to_string();
-
-    let config = ClientConfig::new(
-        login.clone(),
-        // Need to convert to &str first because AccessToken::to_string masks
-        // the actual token
-        token.access_token.as_str().to_string(),
-    );
 
-    let (mut incoming, client) = IrcClient::new(config);
+    let mut config = match guard.token {
+        Some(ref token) => ClientConfig::new(
+            token.login.to_string(),
+            // Need to convert to &str first because AccessToken::to_string
+            // masks the actual token
+            token.access_token.as_str().to_string(),
+        ),
+        None => {
+            tracing::info!("No access token set, connecting to IRC anonymously");
+            ClientConfig::anonymous()
+        }
+    };
+
+    config.transport = transport.unwrap_or_default();
+    config.collapse_shared_chat = collapse_shared_chat.unwrap_or_default();
+
+    let (mut incoming, mut status, client) = IrcClient::new(config);
+    let queue = guard.queue.clone();
 
     async_runtime::spawn(
         async move {
             while let Some(message) = incoming.recv().await {
-                tracing::trace!(?message, "Received {} message", message.source().command);
+                tracing::trace!(?message, "Received message");
 
                 match message {
                     ServerMessage::Join(ref join) => {
@@ -53,10 +65,33 @@ pub async fn connect_irc(
                     ServerMessage::Part(ref part) => {
                         tracing::info!("Parted {}", part.channel_login);
                     }
+                    ServerMessage::RoomState(ref room_state) => {
+                        queue.update_room_state(room_state).await;
+                    }
+                    ServerMessage::UserState(ref user_state) => {
+                        queue.update_user_state(user_state).await;
+                    }
                     _ => (),
                 }
 
-                channel.send(message).unwrap();
+                // The webview may be reloading, the client keeps running so
+                // later messages reach the new one
+                if let Err(err) = channel.send(message) {
+                    tracing::warn!(%err, "Failed to forward message");
+                }
+            }
+        }
+        .in_current_span(),
+    );
+
+    async_runtime::spawn(
+        async move {
+            while let Some(status) = status.recv().await {
+                tracing::debug!(?status, "IRC connection status changed");
+
+                if let Err(err) = status_channel.send(status) {
+                    tracing::warn!(%err, "Failed to send connection status");
+                }
             }
         }
         .in_current_span(),
@@ -67,3 +102,15 @@ pub async fn connect_irc(
 
     Ok(())
 }
+
+#[tracing::instrument(skip_all)]
+#[tauri::command]
+pub async fn get_irc_metrics(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Option<PoolMetrics>, AppError> {
+    let Some(irc) = state.lock().await.irc.clone() else {
+        return Ok(None);
+    };
+
+    Ok(Some(irc.metrics().await))
+}
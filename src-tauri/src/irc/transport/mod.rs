@@ -0,0 +1,86 @@
+mod tcp;
+mod websocket;
+
+use std::fmt::Debug;
+use std::future::Future;
+
+use either::Either;
+use futures::Sink;
+use futures::stream::FusedStream;
+use rustls::pki_types::InvalidDnsNameError;
+use serde::Deserialize;
+pub use tcp::TcpTransport;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite;
+use tokio_util::codec::LinesCodecError;
+pub use websocket::WsTransport;
+
+use crate::irc::message::{IrcMessage, IrcParseError, ServerMessage};
+
+pub type Incoming = Box<
+    dyn FusedStream<Item = Result<ServerMessage, Either<TransportError, IrcParseError>>>
+        + Unpin
+        + Send
+        + Sync,
+>;
+
+pub type Outgoing = Box<dyn Sink<IrcMessage, Error = TransportError> + Unpin + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum TransportError {
+    #[error(transparent)]
+    WebSocket(#[from] tungstenite::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Codec(#[from] LinesCodecError),
+
+    #[error(transparent)]
+    InvalidDnsName(#[from] InvalidDnsNameError),
+}
+
+/// A connection to the Twitch IRC server that can be split into a stream of
+/// incoming messages and a sink for outgoing messages.
+pub trait Transport: Sized + Send + Sync + Debug + 'static {
+    fn new() -> impl Future<Output = Result<Self, TransportError>> + Send;
+
+    fn split(self) -> (Incoming, Outgoing);
+}
+
+/// The transport used by the connections of an IRC client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// WebSocket connection to `irc-ws.chat.twitch.tv:443`.
+    #[default]
+    WebSocket,
+    /// Raw TCP connection secured with TLS to `irc.chat.twitch.tv:6697`.
+    Tcp,
+}
+
+impl TransportKind {
+    pub async fn connect(self) -> Result<AnyTransport, TransportError> {
+        Ok(match self {
+            TransportKind::WebSocket => AnyTransport::WebSocket(WsTransport::new().await?),
+            TransportKind::Tcp => AnyTransport::Tcp(TcpTransport::new().await?),
+        })
+    }
+}
+
+/// A connected transport of any kind.
+#[derive(Debug)]
+pub enum AnyTransport {
+    WebSocket(WsTransport),
+    Tcp(TcpTransport),
+}
+
+impl AnyTransport {
+    pub fn split(self) -> (Incoming, Outgoing) {
+        match self {
+            AnyTransport::WebSocket(transport) => transport.split(),
+            AnyTransport::Tcp(transport) => transport.split(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::future;
+use std::sync::Arc;
+
+use either::Either;
+use futures::{StreamExt, TryStreamExt, sink};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_util::codec::{FramedRead, LinesCodec};
+
+use super::{Incoming, Outgoing, Transport, TransportError};
+use crate::endpoints::ENDPOINTS;
+use crate::irc::message::{AsRawIrc, IrcMessage, ServerMessage};
+
+/// Upper bound for a single incoming line. Twitch messages including their
+/// tags stay well below this.
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+pub struct TcpTransport {
+    incoming_messages: Incoming,
+    outgoing_messages: Outgoing,
+}
+
+impl std::fmt::Debug for TcpTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpTransport").finish()
+    }
+}
+
+impl Transport for TcpTransport {
+    #[tracing::instrument(name = "irc_tcp_connect")]
+    async fn new() -> Result<TcpTransport, TransportError> {
+        tracing::info!("Connecting to IRC at {}", ENDPOINTS.irc_tls);
+
+        let stream = match connect().await {
+            Ok(stream) => stream,
+            Err(err) => {
+                tracing::error!(%err, "Failed to connect to IRC");
+                return Err(err);
+            }
+        };
+
+        tracing::info!("Connected to IRC");
+
+        let (read_half, write_half) = tokio::io::split(stream);
+
+        let message_stream =
+            FramedRead::new(read_half, LinesCodec::new_with_max_length(MAX_LINE_LENGTH))
+                .map_err(|err| Either::Left(err.into()))
+                .try_filter(|line| future::ready(!line.is_empty()))
+                .and_then(|s| future::ready(ServerMessage::parse(&s).map_err(Either::Right)))
+                .fuse();
+
+        let message_sink = sink::unfold(write_half, |mut write_half, msg: IrcMessage| async move {
+            let line = format!("{}\r\n", msg.as_raw_irc());
+            write_half.write_all(line.as_bytes()).await?;
+            // TLS records stay buffered until flushed, a lone PONG would
+            // otherwise wait for the next message
+            write_half.flush().await?;
+
+            Ok::<_, TransportError>(write_half)
+        });
+
+        Ok(TcpTransport {
+            incoming_messages: Box::new(message_stream),
+            outgoing_messages: Box::new(Box::pin(message_sink)),
+        })
+    }
+
+    fn split(self) -> (Incoming, Outgoing) {
+        (self.incoming_messages, self.outgoing_messages)
+    }
+}
+
+async fn connect() -> Result<tokio_rustls::client::TlsStream<TcpStream>, TransportError> {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let addr = ENDPOINTS.irc_tls.as_str();
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+
+    let domain = ServerName::try_from(host)?.to_owned();
+    let stream = TcpStream::connect(addr).await?;
+
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(domain, stream)
+        .await?;
+
+    Ok(stream)
+}
@@ -1,20 +1,13 @@
 use std::future;
 
 use either::Either;
-use futures::stream::{self, FusedStream};
-use futures::{Sink, SinkExt, StreamExt, TryStreamExt};
+use futures::{SinkExt, StreamExt, TryStreamExt, stream};
 use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::tungstenite::Message;
 
-use crate::irc::message::{AsRawIrc, IrcMessage, IrcParseError};
-
-const TWITCH_IRC_WS_URI: &str = "wss://irc-ws.chat.twitch.tv";
-
-pub type Incoming = Box<
-    dyn FusedStream<Item = Result<IrcMessage, Either<Error, IrcParseError>>> + Unpin + Send + Sync,
->;
-
-pub type Outgoing = Box<dyn Sink<IrcMessage, Error = Error> + Unpin + Send + Sync>;
+use super::{Incoming, Outgoing, Transport, TransportError};
+use crate::endpoints::ENDPOINTS;
+use crate::irc::message::{AsRawIrc, IrcMessage, IrcParseError, ServerMessage};
 
 pub struct WsTransport {
     incoming_messages: Incoming,
@@ -27,16 +20,16 @@ impl std::fmt::Debug for WsTransport {
     }
 }
 
-impl WsTransport {
+impl Transport for WsTransport {
     #[tracing::instrument(name = "irc_ws_connect")]
-    pub async fn new() -> Result<WsTransport, Error> {
-        tracing::info!("Connecting to IRC at {TWITCH_IRC_WS_URI}");
+    async fn new() -> Result<WsTransport, TransportError> {
+        tracing::info!("Connecting to IRC at {}", ENDPOINTS.irc_ws);
 
-        let ws_stream = match connect_async(TWITCH_IRC_WS_URI).await {
+        let ws_stream = match connect_async(&ENDPOINTS.irc_ws).await {
             Ok((stream, _)) => stream,
             Err(err) => {
                 tracing::error!(%err, "Failed to connect to IRC");
-                return Err(err);
+                return Err(err.into());
             }
         };
 
@@ -45,9 +38,9 @@ impl WsTransport {
         let (write_half, read_half) = ws_stream.split();
 
         let message_stream = read_half
-            .map_err(Either::Left)
+            .map_err(|err| Either::Left(err.into()))
             .try_filter_map(|ws_message| {
-                future::ready(Ok::<_, Either<Error, IrcParseError>>(
+                future::ready(Ok::<_, Either<TransportError, IrcParseError>>(
                     if let Message::Text(text) = ws_message {
                         Some(stream::iter(
                             text.lines()
@@ -61,10 +54,11 @@ impl WsTransport {
             })
             .try_flatten()
             .try_filter(|line| future::ready(!line.is_empty()))
-            .and_then(|s| future::ready(IrcMessage::parse(&s).map_err(Either::Right)))
+            .and_then(|s| future::ready(ServerMessage::parse(&s).map_err(Either::Right)))
             .fuse();
 
         let message_sink = write_half
+            .sink_map_err(TransportError::from)
             .with(move |msg: IrcMessage| future::ready(Ok(Message::Text(msg.as_raw_irc().into()))));
 
         Ok(WsTransport {
@@ -73,7 +67,7 @@ impl WsTransport {
         })
     }
 
-    pub fn split(self) -> (Incoming, Outgoing) {
+    fn split(self) -> (Incoming, Outgoing) {
         (self.incoming_messages, self.outgoing_messages)
     }
 }
@@ -3,9 +3,11 @@
 
 use std::sync::{Arc, LazyLock};
 
+use endpoints::ENDPOINTS;
 use eventsub::EventSubClient;
 use irc::IrcClient;
 use providers::seventv::SeventTvClient;
+use queue::MessageQueue;
 use reqwest::header::HeaderMap;
 use tauri::async_runtime::{self, Mutex};
 use tauri::ipc::Invoke;
@@ -15,14 +17,26 @@ use twitch_api::HelixClient;
 use twitch_api::twitch_oauth2::{AccessToken, UserToken};
 
 mod api;
+mod backoff;
 mod emotes;
+mod endpoints;
 mod error;
 mod eventsub;
 mod irc;
 mod log;
 mod providers;
+mod queue;
+mod recent_ids;
 mod server;
 
+/// IRC codec for the benchmarks and fuzz targets, not part of the app's API.
+#[doc(hidden)]
+pub mod __bench {
+    pub use crate::irc::message::{
+        AsRawIrc, ClientMessage, IrcMessage, IrcMessageRef, ServerMessage,
+    };
+}
+
 const CLIENT_ID: &str = "kimne78kx3ncx6brgo4mv6wki5h1ko";
 
 pub static HTTP: LazyLock<reqwest::Client> = LazyLock::new(|| {
@@ -43,6 +57,7 @@ pub struct AppState {
     eventsub: Option<Arc<EventSubClient>>,
     seventv: Option<Arc<SeventTvClient>>,
     seventv_id: Option<String>,
+    queue: Arc<MessageQueue>,
 }
 
 impl Default for AppState {
@@ -54,12 +69,16 @@ impl Default for AppState {
             eventsub: None,
             seventv: None,
             seventv_id: None,
+            queue: Arc::default(),
         }
     }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // SAFETY: Called before the runtime or any plugin starts a thread
+    unsafe { ENDPOINTS.export_helix_url() };
+
     let mut builder = tauri::Builder::default();
     let mut state = AppState::default();
 
@@ -84,6 +103,8 @@ pub fn run() {
             let log_guard = log::init_tracing(app);
             let app_handle = app.handle();
 
+            tracing::debug!(endpoints = ?*ENDPOINTS, "Loaded endpoints");
+
             async_runtime::block_on(async {
                 let stored_token = app_handle
                     .svelte()
@@ -165,6 +186,7 @@ fn get_handler() -> impl Fn(Invoke) -> bool {
         api::chat::leave,
         api::chat::fetch_global_badges,
         api::chat::announce,
+        api::chat::send_message,
         api::moderation::delete_message,
         api::moderation::clear_chat,
         api::moderation::update_held_message,
@@ -188,7 +210,9 @@ fn get_handler() -> impl Fn(Invoke) -> bool {
         api::users::unblock,
         emotes::fetch_global_emotes,
         eventsub::connect_eventsub,
+        eventsub::get_eventsub_metrics,
         irc::connect_irc,
+        irc::get_irc_metrics,
         log::log,
         providers::fetch_recent_messages,
         providers::seventv::connect_seventv,
@@ -1,11 +1,9 @@
 use anyhow::Result;
 use serde::Deserialize;
 
-use crate::emotes::Emote;
 use crate::HTTP;
-
-const BASE_URL: &str = "https://api.betterttv.net/3/cached";
-const CDN_URL: &str = "https://cdn.betterttv.net/emote";
+use crate::emotes::Emote;
+use crate::endpoints::ENDPOINTS;
 
 #[derive(Deserialize)]
 pub struct User {
@@ -28,7 +26,8 @@ impl From<ApiEmote> for Emote {
     fn from(value: ApiEmote) -> Self {
         let id = value.id.clone();
 
-        let candidate = |density: u8| format!("{CDN_URL}/{}/{density}x {density}x", value.id);
+        let candidate =
+            |density: u8| format!("{}/{}/{density}x {density}x", ENDPOINTS.bttv_cdn, value.id);
 
         Emote {
             id,
@@ -43,7 +42,7 @@ impl From<ApiEmote> for Emote {
 
 pub async fn fetch_global_emotes() -> Result<Vec<Emote>> {
     let emotes: Vec<ApiEmote> = HTTP
-        .get(format!("{BASE_URL}/emotes/global"))
+        .get(format!("{}/emotes/global", ENDPOINTS.bttv_api))
         .send()
         .await?
         .json()
@@ -55,7 +54,7 @@ pub async fn fetch_global_emotes() -> Result<Vec<Emote>> {
 
 pub async fn fetch_user_emotes(id: &str) -> Result<Vec<Emote>> {
     let user = HTTP
-        .get(format!("{BASE_URL}/users/twitch/{id}"))
+        .get(format!("{}/users/twitch/{id}", ENDPOINTS.bttv_api))
         .send()
         .await?
         .json::<User>()
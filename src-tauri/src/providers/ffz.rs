@@ -3,10 +3,9 @@ use std::collections::HashMap;
 use anyhow::Result;
 use serde::Deserialize;
 
-use crate::emotes::Emote;
 use crate::HTTP;
-
-const BASE_URL: &str = "https://api.frankerfacez.com/v1";
+use crate::emotes::Emote;
+use crate::endpoints::ENDPOINTS;
 
 #[derive(Deserialize)]
 pub struct Room {
@@ -60,7 +59,7 @@ impl From<&ApiEmote> for Emote {
 
 pub async fn fetch_global_emotes() -> Result<Vec<Emote>> {
     let global_set: GlobalSet = HTTP
-        .get(format!("{BASE_URL}/set/global"))
+        .get(format!("{}/set/global", ENDPOINTS.ffz_api))
         .send()
         .await?
         .json()
@@ -78,7 +77,10 @@ pub async fn fetch_global_emotes() -> Result<Vec<Emote>> {
 }
 
 pub async fn fetch_user_emotes(id: &str) -> Result<Vec<Emote>> {
-    let response = HTTP.get(format!("{BASE_URL}/room/id/{id}")).send().await?;
+    let response = HTTP
+        .get(format!("{}/room/id/{id}", ENDPOINTS.ffz_api))
+        .send()
+        .await?;
     let mut emotes = Vec::new();
 
     if !response.status().is_success() {
@@ -1,10 +1,11 @@
 use serde::Deserialize;
-use tauri::{async_runtime, AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, async_runtime};
 use tracing::Instrument;
 
-use crate::error::Error;
-use crate::irc::message::{IrcMessage, ServerMessage};
 use crate::HTTP;
+use crate::endpoints::ENDPOINTS;
+use crate::error::Error;
+use crate::irc::message::{IrcMessageRef, ServerMessage};
 
 #[derive(Debug, Deserialize)]
 struct RecentMessages {
@@ -21,25 +22,29 @@ pub async fn fetch_recent_messages(app_handle: AppHandle, channel: String, histo
         return;
     }
 
-    async_runtime::spawn(async move {
-        let response: RecentMessages = HTTP
-            .get(format!(
-                "https://recent-messages.robotty.de/api/v2/recent-messages/{channel}?limit={history_limit}",
-            ))
-            .send()
-            .await?
-            .json()
-            .await?;
+    async_runtime::spawn(
+        async move {
+            let response: RecentMessages = HTTP
+                .get(format!(
+                    "{}/recent-messages/{channel}?limit={history_limit}",
+                    ENDPOINTS.recent_messages,
+                ))
+                .send()
+                .await?
+                .json()
+                .await?;
 
-		tracing::info!("Fetched {} recent messages", response.messages.len());
+            tracing::info!("Fetched {} recent messages", response.messages.len());
 
-        let server_messages: Vec<_> = response
-            .messages
-            .into_iter()
-            .filter_map(|m| ServerMessage::try_from(IrcMessage::parse(&m).ok()?).ok())
-            .collect();
+            let server_messages: Vec<_> = response
+                .messages
+                .into_iter()
+                .filter_map(|m| ServerMessage::try_from(&IrcMessageRef::parse(&m).ok()?).ok())
+                .collect();
 
-        app_handle.emit("recentmessages", server_messages).unwrap();
-        Ok::<_, Error>(())
-    }.in_current_span());
+            app_handle.emit("recentmessages", server_messages).unwrap();
+            Ok::<_, Error>(())
+        }
+        .in_current_span(),
+    );
 }
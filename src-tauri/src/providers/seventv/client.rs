@@ -11,10 +11,9 @@ use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::Instrument;
 
+use crate::endpoints::ENDPOINTS;
 use crate::error::Error;
 
-const SEVENTV_WS_URI: &str = "wss://events.7tv.io/v3";
-
 #[derive(Deserialize)]
 struct WebSocketMessage {
     op: u8,
@@ -61,7 +60,7 @@ impl SeventTvClient {
             loop {
 				tracing::info!("Connecting to 7TV Event API");
 
-                let mut stream = match connect_async(SEVENTV_WS_URI).await {
+                let mut stream = match connect_async(&ENDPOINTS.seventv_events).await {
                     Ok((stream, _)) => stream,
                     Err(err) => {
 						tracing::error!(%err, "Failed to connect to 7TV Event API");
@@ -3,11 +3,10 @@ use std::time::SystemTime;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::HTTP;
 use crate::emotes::Emote;
+use crate::endpoints::ENDPOINTS;
 use crate::error::Error;
-use crate::HTTP;
-
-const BASE_URL: &str = "https://7tv.io/v3";
 
 #[derive(Deserialize)]
 
@@ -99,7 +98,7 @@ impl From<ApiEmote> for Emote {
 
 pub async fn fetch_global_emotes() -> Result<Vec<Emote>> {
     let global_set: EmoteSet = HTTP
-        .get(format!("{BASE_URL}/emote-sets/global"))
+        .get(format!("{}/emote-sets/global", ENDPOINTS.seventv_api))
         .send()
         .await?
         .json()
@@ -118,7 +117,10 @@ pub async fn fetch_active_emote_set(id: &str) -> Result<Option<EmoteSet>, Error>
 
     let user: User = HTTP
         // Appending a timestamp parameter to bypass caching
-        .get(format!("{BASE_URL}/users/twitch/{id}?t={timestamp}"))
+        .get(format!(
+            "{}/users/twitch/{id}?t={timestamp}",
+            ENDPOINTS.seventv_api
+        ))
         .send()
         .await?
         .json()
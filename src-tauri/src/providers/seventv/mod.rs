@@ -11,6 +11,7 @@ use tauri::ipc::Channel;
 use tauri::{AppHandle, Manager, State, async_runtime};
 use tokio::sync::Mutex;
 
+use crate::endpoints::ENDPOINTS;
 use crate::error::Error;
 use crate::{AppState, HTTP};
 
@@ -68,7 +69,7 @@ pub async fn send_presence(
     };
 
     let response = HTTP
-        .post(format!("https://7tv.io/v3/users/{id}/presences"))
+        .post(format!("{}/users/{id}/presences", ENDPOINTS.seventv_api))
         .json(&json!({
             "kind": 1,
             "passive": false,
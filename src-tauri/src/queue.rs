@@ -0,0 +1,285 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+use crate::irc::message::{Badge, RoomStateMessage, UserStateMessage};
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+const RATE_LIMIT_GRACE: Duration = Duration::from_secs(1);
+
+const MAX_MESSAGES: usize = 20;
+const MAX_MESSAGES_ELEVATED: usize = 100;
+
+/// Badges that lift the regular send limits and exempt the user from slow
+/// mode.
+const ELEVATED_BADGES: [&str; 2] = ["broadcaster", "moderator"];
+
+/// Badges that only exempt the user from slow mode, VIPs are held to the
+/// regular send limits.
+const SLOW_MODE_EXEMPT_BADGES: [&str; 1] = ["vip"];
+
+#[derive(Clone, Serialize)]
+pub struct QueueStatus {
+    broadcaster_id: String,
+    /// Number of messages waiting to be sent, including the one that was just
+    /// queued.
+    length: usize,
+    /// Milliseconds until the last queued message is expected to be sent.
+    eta: u64,
+}
+
+/// What the user's badges in a channel allow them to skip.
+#[derive(Debug, Default, Clone, Copy)]
+struct Privileges {
+    elevated: bool,
+    skips_slow_mode: bool,
+}
+
+impl Privileges {
+    fn from_badges(badges: &[Badge]) -> Self {
+        let has_any = |names: &[&str]| {
+            badges
+                .iter()
+                .any(|badge| names.contains(&badge.name.as_str()))
+        };
+
+        let elevated = has_any(&ELEVATED_BADGES);
+
+        Privileges {
+            elevated,
+            skips_slow_mode: elevated || has_any(&SLOW_MODE_EXEMPT_BADGES),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ChannelLimits {
+    privileges: Privileges,
+    slow_mode: Duration,
+    /// Send times within the rate limit window.
+    send_times: VecDeque<Instant>,
+    /// Kept apart from `send_times`, slow mode can be longer than the window
+    /// the send times are pruned to.
+    last_sent: Option<Instant>,
+}
+
+impl ChannelLimits {
+    fn max_messages(&self) -> usize {
+        if self.privileges.elevated {
+            MAX_MESSAGES_ELEVATED
+        } else {
+            MAX_MESSAGES
+        }
+    }
+
+    fn record(&mut self, sent_at: Instant) {
+        self.send_times.push_back(sent_at);
+        self.last_sent = Some(sent_at);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(&sent_at) = self.send_times.front() {
+            if sent_at + RATE_LIMIT_WINDOW + RATE_LIMIT_GRACE > now {
+                break;
+            }
+
+            self.send_times.pop_front();
+        }
+    }
+
+    /// Returns how long the next `count` messages have to wait in total before
+    /// the last of them can be sent without being rejected.
+    fn delay_for(&self, now: Instant, count: usize) -> Duration {
+        let mut send_times = self.send_times.clone();
+        let mut last_sent = self.last_sent;
+        let mut next = now;
+
+        for _ in 0..count {
+            while let Some(&sent_at) = send_times.front() {
+                if sent_at + RATE_LIMIT_WINDOW + RATE_LIMIT_GRACE > next {
+                    break;
+                }
+
+                send_times.pop_front();
+            }
+
+            if send_times.len() >= self.max_messages()
+                && let Some(&oldest) = send_times.front()
+            {
+                next = next.max(oldest + RATE_LIMIT_WINDOW + RATE_LIMIT_GRACE);
+                send_times.pop_front();
+            }
+
+            if !self.privileges.skips_slow_mode
+                && !self.slow_mode.is_zero()
+                && let Some(last) = last_sent
+            {
+                next = next.max(last + self.slow_mode);
+            }
+
+            send_times.push_back(next);
+            last_sent = Some(next);
+        }
+
+        next.saturating_duration_since(now)
+    }
+}
+
+#[derive(Default)]
+struct ChannelQueue {
+    /// Held by the message that is currently allowed to send. Tokio's mutex is
+    /// fair, so waiters are let through in the order they were queued.
+    turn: Mutex<()>,
+    waiting: AtomicUsize,
+    limits: Mutex<ChannelLimits>,
+}
+
+/// Schedules outgoing chat messages so they stay within Twitch's send limits
+/// and the channel's slow mode instead of being dropped by the server.
+#[derive(Default)]
+pub struct MessageQueue {
+    channels: Mutex<HashMap<String, Arc<ChannelQueue>>>,
+    // Room states carry both the login and id while user states only carry the
+    // login.
+    logins: Mutex<HashMap<String, String>>,
+    // User states that arrived before the room state of their channel.
+    pending_privileges: Mutex<HashMap<String, Privileges>>,
+}
+
+impl MessageQueue {
+    async fn channel(&self, broadcaster_id: &str) -> Arc<ChannelQueue> {
+        let mut channels = self.channels.lock().await;
+
+        Arc::clone(channels.entry(broadcaster_id.to_string()).or_default())
+    }
+
+    pub async fn update_room_state(&self, room_state: &RoomStateMessage) {
+        self.logins.lock().await.insert(
+            room_state.channel_login.clone(),
+            room_state.channel_id.clone(),
+        );
+
+        let channel = self.channel(&room_state.channel_id).await;
+        let mut limits = channel.limits.lock().await;
+
+        if let Some(privileges) = self
+            .pending_privileges
+            .lock()
+            .await
+            .remove(&room_state.channel_login)
+        {
+            limits.privileges = privileges;
+        }
+
+        if let Some(slow_mode) = room_state.slow_mode {
+            limits.slow_mode = slow_mode;
+
+            tracing::debug!(
+                channel = room_state.channel_login,
+                "Slow mode set to {}s",
+                slow_mode.as_secs()
+            );
+        }
+    }
+
+    pub async fn update_user_state(&self, user_state: &UserStateMessage) {
+        let privileges = Privileges::from_badges(&user_state.badges);
+
+        let Some(broadcaster_id) = self
+            .logins
+            .lock()
+            .await
+            .get(&user_state.channel_login)
+            .cloned()
+        else {
+            self.pending_privileges
+                .lock()
+                .await
+                .insert(user_state.channel_login.clone(), privileges);
+
+            return;
+        };
+
+        let channel = self.channel(&broadcaster_id).await;
+        channel.limits.lock().await.privileges = privileges;
+    }
+
+    /// Waits until a message can be sent in the channel without exceeding the
+    /// send limits and records it as sent.
+    ///
+    /// While the message is waiting, a `messagequeue` event is emitted with the
+    /// queue length and the estimated time until the queue is drained.
+    #[tracing::instrument(skip(self, app_handle))]
+    pub async fn wait_turn(&self, app_handle: &AppHandle, broadcaster_id: &str) {
+        let channel = self.channel(broadcaster_id).await;
+
+        let length = channel.waiting.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let queued = {
+            let limits = channel.limits.lock().await;
+            let eta = limits.delay_for(Instant::now(), length);
+
+            if !eta.is_zero() {
+                tracing::info!("Message queued at position {length}, sending in {eta:?}");
+                emit_status(app_handle, broadcaster_id, length, eta);
+            }
+
+            !eta.is_zero()
+        };
+
+        let _turn = channel.turn.lock().await;
+
+        loop {
+            let delay = {
+                let mut limits = channel.limits.lock().await;
+                let now = Instant::now();
+
+                limits.prune(now);
+                let delay = limits.delay_for(now, 1);
+
+                if delay.is_zero() {
+                    limits.record(now);
+                }
+
+                delay
+            };
+
+            if delay.is_zero() {
+                break;
+            }
+
+            tokio::time::sleep(delay).await;
+        }
+
+        let remaining = channel.waiting.fetch_sub(1, Ordering::SeqCst) - 1;
+        let eta = channel
+            .limits
+            .lock()
+            .await
+            .delay_for(Instant::now(), remaining);
+
+        if queued || remaining > 0 {
+            emit_status(app_handle, broadcaster_id, remaining, eta);
+        }
+    }
+}
+
+fn emit_status(app_handle: &AppHandle, broadcaster_id: &str, length: usize, eta: Duration) {
+    let status = QueueStatus {
+        broadcaster_id: broadcaster_id.to_string(),
+        length,
+        eta: eta.as_millis() as u64,
+    };
+
+    if let Err(err) = app_handle.emit("messagequeue", status) {
+        tracing::warn!(%err, "Failed to emit queue status");
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use super::{ChannelLimits, MAX_MESSAGES, MAX_MESSAGES_ELEVATED, Privileges};
+use crate::irc::message::Badge;
+
+fn badge(name: &str) -> Badge {
+    Badge {
+        name: name.into(),
+        version: "1".into(),
+    }
+}
+
+fn limits(slow_mode: u64) -> ChannelLimits {
+    ChannelLimits {
+        slow_mode: Duration::from_secs(slow_mode),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn slow_mode_outlasts_the_rate_limit_window() {
+    let mut limits = limits(60);
+    let start = Instant::now();
+
+    limits.record(start);
+
+    let now = start + Duration::from_secs(40);
+    limits.prune(now);
+
+    assert!(limits.send_times.is_empty());
+    assert_eq!(limits.delay_for(now, 1), Duration::from_secs(20));
+    assert_eq!(limits.delay_for(now, 2), Duration::from_secs(80));
+}
+
+#[test]
+fn elevated_users_skip_slow_mode() {
+    let mut limits = limits(60);
+    let start = Instant::now();
+
+    limits.privileges = Privileges::from_badges(&[badge("moderator")]);
+    limits.record(start);
+
+    assert_eq!(limits.delay_for(start, 1), Duration::ZERO);
+}
+
+#[test]
+fn full_windows_wait_for_the_oldest_send() {
+    let mut limits = limits(0);
+    let start = Instant::now();
+
+    for i in 0..MAX_MESSAGES {
+        limits.record(start + Duration::from_secs(i as u64));
+    }
+
+    assert_eq!(
+        limits.delay_for(start + Duration::from_secs(20), 1),
+        Duration::from_secs(11)
+    );
+}
+
+#[test]
+fn vips_skip_slow_mode_but_not_the_regular_limit() {
+    let mut limits = limits(60);
+    let start = Instant::now();
+
+    limits.privileges = Privileges::from_badges(&[badge("vip"), badge("subscriber")]);
+    assert_eq!(limits.max_messages(), MAX_MESSAGES);
+
+    limits.record(start);
+    assert_eq!(limits.delay_for(start, 1), Duration::ZERO);
+
+    limits.privileges = Privileges::from_badges(&[badge("broadcaster")]);
+    assert_eq!(limits.max_messages(), MAX_MESSAGES_ELEVATED);
+}
@@ -0,0 +1,43 @@
+use std::collections::{HashSet, VecDeque};
+
+/// A bounded set of recently seen ids used to drop messages that are delivered
+/// more than once. Once full, the oldest id is evicted for every new one.
+#[derive(Debug)]
+pub struct RecentIds {
+    capacity: usize,
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl RecentIds {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ids: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records the id and returns `true` if it has not been seen before.
+    pub fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+
+        if self.order.len() >= self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+
+        self.ids.insert(id.to_string());
+        self.order.push_back(id.to_string());
+
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.order.clear();
+    }
+}
@@ -1,6 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
 import { SvelteMap } from "svelte/reactivity";
-import { PUBLIC_TWITCH_CLIENT_ID } from "$env/static/public";
 import { commands } from "./commands";
 import type { Command } from "./commands/util";
 import { log } from "./log";
@@ -9,24 +8,16 @@ import type { Message } from "./message";
 import { settings } from "./settings";
 import type { EmoteSet } from "./seventv";
 import { app } from "./state.svelte";
-import type { Emote, JoinedChannel } from "./tauri";
+import type { Emote, JoinedChannel, QueueStatus, SentMessage } from "./tauri";
 import type { Badge, BadgeSet, Cheermote, Stream } from "./twitch/api";
 import { User } from "./user.svelte";
 import { find } from "./util";
 
-const RATE_LIMIT_WINDOW = 30 * 1000;
-const RATE_LIMIT_GRACE = 1000;
-
 export class Channel {
 	#stream = $state<Stream | null>(null);
 	#bypassNext = false;
 	#lastRecentAt: number | null = null;
 
-	#lastMessage: number[] = [];
-	#lastMessageElevated: number[] = [];
-	#lastHitSpdAt: number;
-	#lastHitAmtAt: number;
-
 	public readonly badges = new SvelteMap<string, Record<string, Badge>>();
 	public readonly commands = new SvelteMap<string, Command>();
 	public readonly emotes = new SvelteMap<string, Emote>();
@@ -54,6 +45,16 @@ export class Channel {
 	 */
 	public error = $state<string>("");
 
+	/**
+	 * The status of messages waiting to be sent if any are queued.
+	 */
+	public queue = $state<QueueStatus | null>(null);
+
+	/**
+	 * Whether the channel is being rejoined after its chat connection closed.
+	 */
+	public reconnecting = $state(false);
+
 	public constructor(
 		/**
 		 * The user for the channel.
@@ -65,11 +66,6 @@ export class Channel {
 
 		this.user.isBroadcaster = true;
 		this.viewers.set(user.id, user);
-
-		const now = performance.now();
-
-		this.#lastHitSpdAt = now - RATE_LIMIT_WINDOW * 2;
-		this.#lastHitAmtAt = now - RATE_LIMIT_WINDOW * 2;
 	}
 
 	public static async join(login: string) {
@@ -81,7 +77,9 @@ export class Channel {
 		let channel = app.channels.find((c) => c.user.username === login);
 
 		if (!channel) {
-			const user = new User(joined.user);
+			// Only the chat of channels joined without being logged in is
+			// available
+			const user = joined.user ? new User(joined.user) : User.fromLogin(login);
 			channel = new Channel(user);
 		}
 
@@ -201,9 +199,6 @@ export class Channel {
 			return;
 		}
 
-		const rateLimited = this.#checkRateLimit(elevated);
-		if (rateLimited) return;
-
 		if (!elevated && settings.state.chat.bypassDuplicate && this.history.at(-1) === message) {
 			this.#bypassNext = !this.#bypassNext;
 
@@ -216,37 +211,29 @@ export class Channel {
 
 		log.info(`Sending message in ${this.user.username} (${this.user.id})`);
 
-		const response = await fetch("https://api.twitch.tv/helix/chat/messages", {
-			method: "POST",
-			headers: {
-				"Content-Type": "application/json",
-				"Client-ID": PUBLIC_TWITCH_CLIENT_ID,
-				Authorization: `Bearer ${settings.state.user?.token}`,
-			},
-			body: JSON.stringify({
-				broadcaster_id: this.user.id,
-				sender_id: user.id,
-				reply_parent_message_id: replyId,
-				message,
-			}),
-		});
-
-		const body = await response.json();
 		const sysmsg = new SystemMessage();
 
-		if (body.status === 429) {
-			log.warn(`Rate limit exceeded: ${body.message}`);
-			this.addMessage(sysmsg.setText(body.message));
-		} else if (response.ok) {
-			if (body.data[0].is_sent) {
+		try {
+			const sent = await invoke<SentMessage>("send_message", {
+				broadcasterId: this.user.id,
+				login: this.user.username,
+				message,
+				replyId: replyId ?? null,
+				overIrc: settings.state.chat.sendOverIrc ?? false,
+			});
+
+			if (sent.is_sent) {
 				log.info("Message sent");
 				await invoke("send_presence", { channelId: this.user.id });
-			} else {
-				const reason = body.data[0].drop_reason.message;
+			} else if (sent.drop_reason) {
+				const reason = sent.drop_reason.message;
 
-				log.warn(`Message dropped: ${reason}`);
+				log.warn(`Message dropped (${sent.drop_reason.code}): ${reason}`);
 				this.addMessage(sysmsg.setText(reason));
 			}
+		} catch (error) {
+			log.warn(`Failed to send message: ${error}`);
+			this.addMessage(sysmsg.setText(String(error)));
 		}
 	}
 
@@ -259,37 +246,4 @@ export class Channel {
 		this.ephemeral = true;
 		return this;
 	}
-
-	#checkRateLimit(elevated: boolean) {
-		const now = performance.now();
-
-		const queue = elevated ? this.#lastMessageElevated : this.#lastMessage;
-		const maxMsgCount = elevated ? 99 : 19;
-		const minMsgOffset = elevated ? 100 : 1100;
-
-		const last = queue.at(-1);
-
-		if (last && last + minMsgOffset > now) {
-			if (this.#lastHitSpdAt + RATE_LIMIT_WINDOW < now) {
-				this.#lastHitSpdAt = now;
-			}
-
-			return true;
-		}
-
-		while (queue.length && queue[0] + RATE_LIMIT_WINDOW + RATE_LIMIT_GRACE < now) {
-			queue.shift();
-		}
-
-		if (queue.length >= maxMsgCount) {
-			if (this.#lastHitAmtAt + RATE_LIMIT_WINDOW < now) {
-				this.#lastHitAmtAt = now;
-			}
-
-			return true;
-		}
-
-		queue.push(now);
-		return false;
-	}
 }
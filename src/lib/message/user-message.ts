@@ -66,6 +66,9 @@ export class UserMessage extends Message {
 			is_mod: false,
 			is_subscriber: false,
 			is_recent: false,
+			paid_chat: null,
+			custom_reward_id: null,
+			power_up: null,
 			is_returning_chatter: false,
 			reply: null,
 			sender,
@@ -134,6 +137,27 @@ export class UserMessage extends Message {
 		return "is_highlighted" in this.data && this.data.is_highlighted;
 	}
 
+	/**
+	 * The payment attached to the message if it was sent with Paid Chat.
+	 */
+	public get paidChat() {
+		return "paid_chat" in this.data ? this.data.paid_chat : null;
+	}
+
+	/**
+	 * The id of the channel points reward redeemed with the message.
+	 */
+	public get rewardId() {
+		return "custom_reward_id" in this.data ? this.data.custom_reward_id : null;
+	}
+
+	/**
+	 * The Power-up applied to the message if one was used.
+	 */
+	public get powerUp() {
+		return "power_up" in this.data ? this.data.power_up : null;
+	}
+
 	/**
 	 * Whether the message is an action i.e. sent with `/me`.
 	 */
@@ -152,7 +176,12 @@ export class UserMessage extends Message {
 	 * The event associated with the message if it's a `USERNOTICE` message.
 	 */
 	public get event() {
-		return "event" in this.data ? this.data.event : null;
+		if (!("event" in this.data)) return null;
+
+		// Events shared from another channel of a Shared Chat session are shown
+		// like events of the channel itself
+		const { event } = this.data;
+		return event.type === "shared_chat_notice" ? event.event : event;
 	}
 
 	/**
@@ -16,6 +16,9 @@ export interface MessageHistorySettings {
 }
 
 export interface ChatSettings {
+	transport: "websocket" | "tcp";
+	sendOverIrc: boolean;
+	collapseSharedChat: boolean;
 	bypassDuplicate: boolean;
 	mentionStyle: "none" | "colored" | "painted";
 	localizedNames: boolean;
@@ -87,6 +90,9 @@ export const settings = new RuneStore<Settings>("settings", {
 		},
 	},
 	chat: {
+		transport: "websocket",
+		sendOverIrc: false,
+		collapseSharedChat: false,
 		bypassDuplicate: true,
 		mentionStyle: "painted",
 		localizedNames: true,
@@ -29,10 +29,31 @@ export interface FullChannel {
 	stream: Stream | null;
 }
 
-export interface JoinedChannel extends FullChannel {
-	id: string;
+export interface JoinedChannel extends Omit<FullChannel, "user"> {
+	/**
+	 * `null` when joined without being logged in.
+	 */
+	id: string | null;
+	user: UserWithColor | null;
 	emotes: Record<string, Emote>;
 	emote_set: EmoteSet | null;
 	cheermotes: Cheermote[];
 	badges: BadgeSet[];
 }
+
+export interface DropReason {
+	code: string;
+	message: string;
+}
+
+export interface SentMessage {
+	message_id: string | null;
+	is_sent: boolean;
+	drop_reason: DropReason | null;
+}
+
+export interface QueueStatus {
+	broadcaster_id: string;
+	length: number;
+	eta: number;
+}
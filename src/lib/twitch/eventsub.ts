@@ -310,3 +310,50 @@ export interface NotificationPayload {
 	subscription: { type: string };
 	event: SubscriptionEvent;
 }
+
+export interface EventSubConnected {
+	type: "connected";
+	session_id: string;
+}
+
+export interface EventSubStalled {
+	type: "stalled";
+	timeout: number;
+}
+
+export interface EventSubDisconnected {
+	type: "disconnected";
+	reason: string;
+	retry_in: number;
+}
+
+export interface EventSubSubscriptionFailed {
+	type: "subscriptionfailed";
+	channel: string | null;
+	event: string;
+	error: string;
+}
+
+export type EventSubStatus =
+	| EventSubConnected
+	| EventSubStalled
+	| EventSubDisconnected
+	| EventSubSubscriptionFailed;
+
+export interface EventSubSessionMetrics {
+	session_id: string | null;
+	subscriptions: number;
+}
+
+export interface FailedSubscription {
+	channel: string | null;
+	event: string;
+	error: string;
+}
+
+export interface EventSubMetrics {
+	sessions: EventSubSessionMetrics[];
+	total_cost: number;
+	max_total_cost: number | null;
+	failed: FailedSubscription[];
+}
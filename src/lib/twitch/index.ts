@@ -1,11 +1,12 @@
 import { Channel, invoke } from "@tauri-apps/api/core";
+import { goto } from "$app/navigation";
 import { handlers } from "$lib/handlers";
 import { log } from "$lib/log";
 import { settings } from "$lib/settings";
 import type { DispatchPayload } from "$lib/seventv";
 import { app } from "$lib/state.svelte";
-import type { NotificationPayload } from "./eventsub";
-import type { IrcMessage } from "./irc";
+import type { EventSubStatus, NotificationPayload } from "./eventsub";
+import type { ConnectionStatus, IrcMessage } from "./irc";
 
 export const SCOPES = [
 	// Channel
@@ -61,28 +62,82 @@ export const SCOPES = [
 ];
 
 export async function connect() {
-	if (!settings.state.user || app.connected) return;
+	if (app.connected) return;
 
 	const ircChannel = new Channel<IrcMessage>(async (message) => {
+		if (message.type === "join") {
+			setReconnecting([message.channel_login], false);
+		}
+
 		await handle(message.type, message);
 	});
 
+	const ircStatusChannel = new Channel<ConnectionStatus>(async (status) => {
+		if (status.type === "closed" || status.type === "rejoining") {
+			setReconnecting(status.channels, true);
+		} else if (status.type === "authfailed") {
+			// The token expired or was revoked, chat stays disconnected until
+			// the user logs in again
+			log.warn(`Chat login failed: ${status.reason.message}`);
+
+			settings.state.user = null;
+			app.connected = false;
+
+			await goto("/auth/login");
+		} else if (status.type === "capabilitiesrejected") {
+			// Logging in again wouldn't change the server's answer
+			log.error(`Chat connection refused: ${status.reason.message}`);
+		}
+	});
+
 	const eventsubChannel = new Channel<NotificationPayload>(async (message) => {
 		await handle(message.subscription.type, message.event);
 	});
 
+	const eventsubStatusChannel = new Channel<EventSubStatus>((status) => {
+		if (status.type === "stalled") {
+			log.warn(`EventSub connection stalled after ${status.timeout}ms, reconnecting`);
+		} else if (status.type === "disconnected") {
+			log.warn(`EventSub disconnected: ${status.reason}, retrying in ${status.retry_in}ms`);
+		} else if (status.type === "subscriptionfailed") {
+			const target = status.channel ? ` in ${status.channel}` : "";
+			log.warn(`Failed to subscribe to ${status.event}${target}: ${status.error}`);
+		}
+	});
+
 	const seventvChannel = new Channel<DispatchPayload>(async (message) => {
 		await handle(message.type, "object" in message.body ? message.body.object : message.body);
 	});
 
-	await invoke("connect_irc", { channel: ircChannel });
-	await invoke("connect_eventsub", { channel: eventsubChannel });
+	await invoke("connect_irc", {
+		channel: ircChannel,
+		statusChannel: ircStatusChannel,
+		transport: settings.state.chat.transport ?? "websocket",
+		collapseSharedChat: settings.state.chat.collapseSharedChat ?? false,
+	});
+
+	// Chat can be read anonymously, but EventSub needs an access token
+	if (settings.state.user) {
+		await invoke("connect_eventsub", {
+			channel: eventsubChannel,
+			statusChannel: eventsubStatusChannel,
+		});
+	}
+
 	await invoke("connect_seventv", { channel: seventvChannel });
 
 	app.connected = true;
 	log.info("All connections established");
 }
 
+function setReconnecting(logins: string[], reconnecting: boolean) {
+	for (const channel of app.channels) {
+		if (logins.includes(channel.user.username)) {
+			channel.reconnecting = reconnecting;
+		}
+	}
+}
+
 async function handle(key: string, payload: any) {
 	if (!app.joined) return;
 
@@ -33,6 +33,7 @@ export interface ClearMsgMessage {
 	is_action: boolean;
 	is_recent: boolean;
 	server_timestamp: number;
+	shared_chat: SharedChatSource | null;
 }
 
 export interface JoinMessage {
@@ -42,17 +43,49 @@ export interface JoinMessage {
 }
 
 export type NoticeMessageId =
+	| "already_banned"
+	| "bad_ban_admin"
+	| "bad_ban_anon"
+	| "bad_ban_broadcaster"
+	| "bad_ban_mod"
+	| "bad_ban_self"
+	| "bad_ban_staff"
+	| "ban_success"
+	| "bad_timeout_admin"
+	| "bad_timeout_anon"
+	| "bad_timeout_broadcaster"
+	| "bad_timeout_duration"
+	| "bad_timeout_mod"
+	| "bad_timeout_self"
+	| "bad_timeout_staff"
+	| "timeout_success"
+	| "bad_unban_no_ban"
+	| "unban_success"
+	| "untimeout_banned"
+	| "untimeout_success"
 	| "emote_only_on"
 	| "emote_only_off"
+	| "already_emote_only_on"
+	| "already_emote_only_off"
 	| "followers_on"
 	| "followers_on_zero"
 	| "followers_off"
+	| "r9k_on"
+	| "r9k_off"
+	| "already_r9k_on"
+	| "already_r9k_off"
+	| "slow_on"
+	| "slow_off"
+	| "subs_on"
+	| "subs_off"
+	| "already_subs_on"
+	| "already_subs_off"
 	| "msg_banned"
 	| "msg_bad_characters"
 	| "msg_channel_blocked"
 	| "msg_channel_suspended"
 	| "msg_duplicate"
-	| "msg_emotesonly"
+	| "msg_emoteonly"
 	| "msg_followersonly"
 	| "msg_followersonly_followed"
 	| "msg_followersonly_zero"
@@ -66,13 +99,9 @@ export type NoticeMessageId =
 	| "msg_suspended"
 	| "msg_timedout"
 	| "msg_verified_email"
-	| "r9k_on"
-	| "r9k_off"
-	| "slow_on"
-	| "slow_off"
-	| "subs_on"
-	| "subs_off"
 	| "tos_ban"
+	| "no_permission"
+	| "invalid_user"
 	| "unrecognized_cmd"
 	| ({} & string);
 
@@ -114,6 +143,13 @@ export interface Emote {
 	code: string;
 }
 
+export interface SharedChatSource {
+	channel_id: string;
+	message_id: string | null;
+	badges: Badge[];
+	badge_info: Badge[];
+}
+
 export interface BaseUserMessage {
 	channel_login: string;
 	channel_id: string;
@@ -126,6 +162,7 @@ export interface BaseUserMessage {
 	deleted: boolean;
 	is_recent: boolean;
 	server_timestamp: number;
+	shared_chat: SharedChatSource | null;
 }
 
 export interface ReplyParent {
@@ -144,6 +181,18 @@ export interface Reply {
 	thread: ReplyThread;
 }
 
+export interface PaidChat {
+	amount: number;
+	currency: string;
+	exponent: number;
+	level: string;
+	is_system_message: boolean;
+}
+
+export type PowerUp =
+	| { type: "gigantified_emote" }
+	| { type: "animated"; animation_id: string | null };
+
 export interface PrivmsgMessage extends BaseUserMessage {
 	type: "privmsg";
 	message_text: string;
@@ -155,6 +204,9 @@ export interface PrivmsgMessage extends BaseUserMessage {
 	is_mod: boolean;
 	is_subscriber: boolean;
 	bits: number | null;
+	paid_chat: PaidChat | null;
+	custom_reward_id: string | null;
+	power_up: PowerUp | null;
 }
 
 export interface SubGiftPromo {
@@ -241,6 +293,54 @@ export interface CommunityPayForwardEvent {
 	gifter: BasicUser;
 }
 
+export interface ViewerMilestoneEvent {
+	type: "viewer_milestone";
+	category: string;
+	value: number;
+	reward: number | null;
+}
+
+export interface CharityDonationEvent {
+	type: "charity_donation";
+	charity_name: string;
+	amount: number;
+	currency: string;
+	exponent: number;
+}
+
+export interface StandardPayForwardEvent {
+	type: "standard_pay_forward";
+	gifter: BasicUser | null;
+	recipient: BasicUser;
+}
+
+export interface OneTapGiftEvent {
+	type: "one_tap_gift";
+	gift_id: string;
+	bits_spent: number;
+}
+
+export interface PrimeCommunityGiftReceivedEvent {
+	type: "prime_community_gift_received";
+	gift_name: string;
+	middle_man: string | null;
+	recipient: string | null;
+}
+
+export interface RewardGiftEvent {
+	type: "reward_gift";
+	domain: string;
+	selected_count: number;
+	total_reward_count: number;
+	trigger_amount: number;
+	trigger_type: string;
+}
+
+export interface SharedChatNoticeEvent {
+	type: "shared_chat_notice";
+	event: Exclude<UserNoticeEvent, SharedChatNoticeEvent>;
+}
+
 export type UserNoticeEvent =
 	| AnnouncementEvent
 	| SubOrResubEvent
@@ -254,7 +354,14 @@ export type UserNoticeEvent =
 	| AnonGiftPaidUpgradeEvent
 	| RitualEvent
 	| BitsBadgeTierEvent
-	| CommunityPayForwardEvent;
+	| CommunityPayForwardEvent
+	| ViewerMilestoneEvent
+	| CharityDonationEvent
+	| StandardPayForwardEvent
+	| OneTapGiftEvent
+	| PrimeCommunityGiftReceivedEvent
+	| RewardGiftEvent
+	| SharedChatNoticeEvent;
 
 export interface UserNoticeMessage extends BaseUserMessage {
 	type: "usernotice";
@@ -273,6 +380,87 @@ export type IrcMessage =
 	| PrivmsgMessage
 	| UserNoticeMessage;
 
+export interface ConnectionError {
+	kind:
+		| "Connect"
+		| "ConnectTimeout"
+		| "Incoming"
+		| "Outgoing"
+		| "IrcParse"
+		| "ReconnectCmd"
+		| "PingTimeout"
+		| "RemoteUnexpectedlyClosedConnection"
+		| "ConnectionClosed"
+		| "AuthFailed"
+		| "CapabilitiesRejected";
+	message: string;
+}
+
+export interface ConnectionInitializing {
+	type: "initializing";
+	connection_id: number;
+}
+
+export interface ConnectionOpen {
+	type: "open";
+	connection_id: number;
+	channels: string[];
+	capabilities: string[];
+}
+
+export interface ConnectionClosed {
+	type: "closed";
+	connection_id: number;
+	reason: ConnectionError;
+	channels: string[];
+	retry_in: number;
+}
+
+export interface ConnectionAuthFailed {
+	type: "authfailed";
+	connection_id: number;
+	reason: ConnectionError;
+	channels: string[];
+}
+
+export interface ConnectionCapabilitiesRejected {
+	type: "capabilitiesrejected";
+	connection_id: number;
+	reason: ConnectionError;
+	channels: string[];
+}
+
+export interface ConnectionRejoining {
+	type: "rejoining";
+	connection_id: number;
+	channels: string[];
+}
+
+export type ConnectionStatus =
+	| ConnectionInitializing
+	| ConnectionOpen
+	| ConnectionClosed
+	| ConnectionAuthFailed
+	| ConnectionCapabilitiesRejected
+	| ConnectionRejoining;
+
+export interface ConnectionMetrics {
+	connection_id: number;
+	channels: string[];
+	rtt: number | null;
+	messages_in: number;
+	messages_out: number;
+	messages_in_rate: number;
+	messages_out_rate: number;
+	uptime: number;
+}
+
+export interface PoolMetrics {
+	/** Replaced connections across the whole pool, not per connection. */
+	reconnects: number;
+	connections: ConnectionMetrics[];
+}
+
 export type IrcMessageMap = {
 	[K in IrcMessage["type"]]: Extract<IrcMessage, { type: K }>;
 };
@@ -156,6 +156,28 @@ export class User implements PartialUser {
 		return user;
 	}
 
+	/**
+	 * Creates a user known only by their login, e.g. the broadcaster of a
+	 * channel joined without being logged in. The id is empty since it can't
+	 * be looked up.
+	 */
+	public static fromLogin(login: string) {
+		return new User({
+			data: {
+				id: "",
+				created_at: "0",
+				login,
+				display_name: login,
+				description: "",
+				profile_image_url: "",
+				offline_image_url: "",
+				type: "",
+				broadcaster_type: "",
+			},
+			color: null,
+		});
+	}
+
 	public static fromBasic(data: WithBasicUser) {
 		return this.fromBare({
 			id: data.user_id,